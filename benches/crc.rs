@@ -0,0 +1,31 @@
+//! Time saved by [`parser::tcp_frame_trusted`] not computing the CRC,
+//! run with `cargo bench --bench crc`
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use nom_teltonika::*;
+
+fn bench_crc(c: &mut Criterion) {
+    // Largest frame possible from the records of the sample file
+    let buffer = std::fs::read("tests/test.bin").unwrap();
+    let mut frame = AVLFrame::try_from(buffer.as_slice()).unwrap();
+    frame.records = frame.records.iter().cycle().take(255).cloned().collect();
+    let input = frame.to_bytes().unwrap();
+    println!("frame of {} bytes", input.len());
+
+    let mut group = c.benchmark_group("crc");
+    group.bench_function("tcp_frame", |b| {
+        b.iter(|| parser::tcp_frame(black_box(&input)).is_ok())
+    });
+    group.bench_function("tcp_frame_trusted", |b| {
+        b.iter(|| parser::tcp_frame_trusted(black_box(&input)).is_ok())
+    });
+    group.bench_function("crc16 alone", |b| {
+        b.iter(|| crc16(black_box(&input[8..input.len() - 4])))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_crc);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+//! Time and allocations taken to parse each kind of frame,
+//! run with `cargo bench --bench parse`
+//!
+//! Next to the time, the allocations of a parse are printed. Since the records and
+//! the IO events of each record are parsed straight into their final vectors, one
+//! allocation for the records and one for the IO events of each record are left,
+//! plus the IMEI of datagrams and the command responses:
+//!
+//! | Input             | Allocations | Before |
+//! |-------------------|-------------|--------|
+//! | codec 8           | 2           | 7      |
+//! | codec 8 extended  | 2           | 7      |
+//! | codec 16          | 3           | 7      |
+//! | codec 12 response | 2           | 7      |
+//! | UDP datagram      | 3           | 5      |
+//! | sample file       | 7           | 32     |
+//! | 255 records       | 256         | 1282   |
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nom_teltonika::*;
+
+/// System allocator counting the allocations made, reallocations included
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(f: impl Fn() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+struct Input {
+    name: &'static str,
+    bytes: Vec<u8>,
+}
+
+fn inputs() -> Vec<Input> {
+    let fixtures = [
+        ("codec 8", "000000000000003608010000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E0000000000000000010000C7CF"),
+        ("codec 8 extended", "000000000000004A8E010000016B412CEE000100000000000000000000000000000000010005000100010100010011001D00010010015E2C880002000B000000003544C87A000E000000001DD7E06A00000100002994"),
+        ("codec 16", "000000000000005F10020000016BDBC7833000000000000000000000000000000000000B05040200010000030002000B00270042563A00000000016BDBC7871800000000000000000000000000000000000B05040200010000030002000B00260042563A00000200005FB3"),
+        ("codec 12 response", "00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3"),
+        ("UDP datagram", "003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001"),
+    ];
+    let mut inputs: Vec<_> = fixtures
+        .into_iter()
+        .map(|(name, fixture)| Input {
+            name,
+            bytes: hex::decode(fixture).unwrap(),
+        })
+        .collect();
+
+    let buffer = std::fs::read("tests/test.bin").unwrap();
+    let mut frame = AVLFrame::try_from(buffer.as_slice()).unwrap();
+    inputs.push(Input {
+        name: "sample file",
+        bytes: frame.to_bytes().unwrap(),
+    });
+
+    // Largest frame possible from the records of the sample file
+    frame.records = frame.records.iter().cycle().take(255).cloned().collect();
+    inputs.push(Input {
+        name: "255 records",
+        bytes: frame.to_bytes().unwrap(),
+    });
+    inputs
+}
+
+fn parse(input: &[u8]) -> bool {
+    if input.starts_with(&[0, 0, 0, 0]) {
+        parser::tcp_frame(input).is_ok()
+    } else {
+        parser::udp_datagram(input).is_ok()
+    }
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let inputs = inputs();
+    for input in &inputs {
+        assert!(parse(&input.bytes), "{} does not parse", input.name);
+        println!(
+            "{}: {} bytes, {} allocations per parse",
+            input.name,
+            input.bytes.len(),
+            allocations(|| parse(&input.bytes))
+        );
+    }
+
+    let mut group = c.benchmark_group("parse");
+    for input in &inputs {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(input.name),
+            &input.bytes,
+            |b, input| b.iter(|| parse(black_box(input))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
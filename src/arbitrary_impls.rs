@@ -0,0 +1,177 @@
+//! [`Arbitrary`] implementations generating values the encoder accepts,
+//! records are generated for the codec of their frame
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use chrono::{TimeZone, Utc};
+
+use crate::{
+    AVLDatagram, AVLEventIO, AVLEventIOValue, AVLFrame, AVLRecord, Codec, EventGenerationCause,
+    Imei, Priority, IMEI_LENGTH,
+};
+
+/// Last millisecond of the year 9999
+const MAX_TIMESTAMP: i64 = 253_402_300_799_999;
+
+fn avl_codec(u: &mut Unstructured<'_>) -> Result<Codec> {
+    u.choose(&[Codec::C8, Codec::C8Ext, Codec::C16]).copied()
+}
+
+fn io_value(u: &mut Unstructured<'_>, codec: Codec) -> Result<AVLEventIOValue> {
+    let widths = if codec == Codec::C8Ext { 5 } else { 4 };
+    Ok(match u.choose_index(widths)? {
+        0 => AVLEventIOValue::U8(u.arbitrary()?),
+        1 => AVLEventIOValue::U16(u.arbitrary()?),
+        2 => AVLEventIOValue::U32(u.arbitrary()?),
+        3 => AVLEventIOValue::U64(u.arbitrary()?),
+        _ => {
+            let mut bytes: Vec<u8> = u.arbitrary()?;
+            bytes.truncate(u16::MAX as usize);
+            AVLEventIOValue::Variable(bytes)
+        }
+    })
+}
+
+/// Record encodable with `codec`: ids and counts fitting its widths,
+/// a generation type only for [`Codec::C16`] and variable values only for [`Codec::C8Ext`]
+fn record(u: &mut Unstructured<'_>, codec: Codec) -> Result<AVLRecord> {
+    let id = |u: &mut Unstructured<'_>| -> Result<u16> {
+        Ok(if codec == Codec::C8 {
+            u8::arbitrary(u)? as u16
+        } else {
+            u.arbitrary()?
+        })
+    };
+    let max_events = if codec == Codec::C8Ext {
+        u16::MAX as usize
+    } else {
+        u8::MAX as usize
+    };
+
+    let timestamp = Utc
+        .timestamp_millis_opt(u.int_in_range(0..=MAX_TIMESTAMP)?)
+        .unwrap();
+    let longitude_raw = u.int_in_range(-1_800_000_000..=1_800_000_000)?;
+    let latitude_raw = u.int_in_range(-900_000_000..=900_000_000)?;
+    let trigger_event_id = id(u)?;
+    let generation_type = if codec == Codec::C16 {
+        Some(*u.choose(&[
+            EventGenerationCause::OnExit,
+            EventGenerationCause::OnEntrance,
+            EventGenerationCause::OnBoth,
+            EventGenerationCause::Reserved,
+            EventGenerationCause::Hysteresis,
+            EventGenerationCause::OnChange,
+            EventGenerationCause::Eventual,
+            EventGenerationCause::Periodical,
+        ])?)
+    } else {
+        None
+    };
+    let mut io_events = vec![];
+    for _ in 0..u.arbitrary_len::<AVLEventIO>()?.min(max_events) {
+        io_events.push(AVLEventIO {
+            id: id(u)?,
+            value: io_value(u, codec)?,
+        });
+    }
+    // In wire order, grouped by width, so that parsing the encoded record gives it back
+    io_events.sort_by_key(|io| match io.value {
+        AVLEventIOValue::U8(_) => 0,
+        AVLEventIOValue::U16(_) => 1,
+        AVLEventIOValue::U32(_) => 2,
+        AVLEventIOValue::U64(_) => 3,
+        AVLEventIOValue::Variable(_)
+        | AVLEventIOValue::Parsed { .. }
+        | AVLEventIOValue::ParseFailed { .. } => 4,
+    });
+
+    Ok(AVLRecord {
+        timestamp,
+        priority: *u.choose(&[Priority::Low, Priority::High, Priority::Panic])?,
+        longitude: longitude_raw as f64 / 10000000.0,
+        latitude: latitude_raw as f64 / 10000000.0,
+        longitude_raw,
+        latitude_raw,
+        altitude: u.arbitrary()?,
+        angle: u.int_in_range(0..=359)?,
+        satellites: u.arbitrary()?,
+        speed: u.arbitrary()?,
+        trigger_event_id,
+        generation_type,
+        io_events,
+    })
+}
+
+fn records(u: &mut Unstructured<'_>, codec: Codec) -> Result<Vec<AVLRecord>> {
+    let mut records = vec![];
+    for _ in 0..u.arbitrary_len::<AVLRecord>()?.min(u8::MAX as usize) {
+        records.push(record(u, codec)?);
+    }
+    Ok(records)
+}
+
+impl<'a> Arbitrary<'a> for Imei {
+    /// Valid Luhn check digit included
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut digits = String::with_capacity(IMEI_LENGTH);
+        for _ in 0..IMEI_LENGTH - 1 {
+            digits.push(char::from(b'0' + u.int_in_range(0..=9)?));
+        }
+        let imei = (0..=9)
+            .map(|check| format!("{digits}{check}"))
+            .find_map(|imei| Imei::parse_luhn(&imei).ok())
+            .unwrap();
+        Ok(imei)
+    }
+}
+
+impl<'a> Arbitrary<'a> for AVLEventIOValue {
+    /// Value of any width, [`AVLEventIOValue::Parsed`] only comes from a [`crate::VariableParser`]
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        io_value(u, Codec::C8Ext)
+    }
+}
+
+impl<'a> Arbitrary<'a> for AVLRecord {
+    /// Record of a random AVL codec
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let codec = avl_codec(u)?;
+        record(u, codec)
+    }
+}
+
+impl<'a> Arbitrary<'a> for AVLFrame {
+    /// Frame passing [`AVLFrame::validate`], holding the CRC of its encoding
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let codec = avl_codec(u)?;
+        let mut frame = AVLFrame {
+            codec,
+            records: records(u, codec)?,
+            crc16: 0,
+        };
+        let bytes = frame
+            .to_bytes()
+            .expect("records are generated for the codec");
+        frame.crc16 = u32::from_be_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        Ok(frame)
+    }
+}
+
+impl<'a> Arbitrary<'a> for AVLDatagram {
+    /// Datagram passing [`AVLDatagram::validate`]
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let codec = avl_codec(u)?;
+        let mut datagram = AVLDatagram {
+            packet_id: u.arbitrary()?,
+            avl_packet_id: u.arbitrary()?,
+            imei: u.arbitrary()?,
+            codec,
+            records: records(u, codec)?,
+        };
+        // The length prefix is 2 bytes wide
+        while datagram.validate().is_err() {
+            datagram.records.pop();
+        }
+        Ok(datagram)
+    }
+}
@@ -0,0 +1,158 @@
+/// IBM CRC16 Algorithm
+///
+/// Uses 0xA001 polynomial
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = Crc16::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// CRC16 CCITT Algorithm (CRC-16/CCITT-FALSE)
+///
+/// Uses 0x1021 polynomial, 0xFFFF initial value and no reflection.
+/// Not part of the Teltonika protocol, some integrations use it in place of [`crc16`]
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _bit in 0..8 {
+            let carry = crc & 0x8000;
+            crc <<= 1;
+            if carry != 0 {
+                crc ^= 0x1021;
+            }
+        }
+    }
+    crc
+}
+
+/// Algorithm used to compute the CRC of a frame
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum CrcAlgorithm {
+    /// [`crc16`], as per the specification
+    #[default]
+    Ibm,
+    /// [`crc16_ccitt`]
+    Ccitt,
+}
+
+impl CrcAlgorithm {
+    pub fn checksum(&self, data: &[u8]) -> u16 {
+        match self {
+            Self::Ibm => crc16(data),
+            Self::Ccitt => crc16_ccitt(data),
+        }
+    }
+}
+
+/// Bytes of a TCP frame a CRC can be computed over, see [`crate::parser::crc_candidates`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CrcRange {
+    /// The data field, from the codec to the last record count, as per the specification
+    Data,
+    /// The data field without the last record count
+    DataWithoutLastCount,
+    /// The data field without the codec
+    DataWithoutCodec,
+    /// The data length followed by the data field
+    LengthAndData,
+    /// Everything before the CRC, preamble included
+    Frame,
+}
+
+impl CrcRange {
+    pub const ALL: [CrcRange; 5] = [
+        Self::Data,
+        Self::DataWithoutLastCount,
+        Self::DataWithoutCodec,
+        Self::LengthAndData,
+        Self::Frame,
+    ];
+}
+
+/// IBM CRC16 over data split in multiple chunks, without concatenating them
+///
+/// Same as [`crc16`] over the concatenation of all the chunks
+pub fn crc16_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> u16 {
+    let mut crc = Crc16::new();
+    for chunk in chunks {
+        crc.update(chunk);
+    }
+    crc.finish()
+}
+
+/// Incremental IBM CRC16, for data that is not available all at once
+///
+/// ```rust
+/// use nom_teltonika::{crc16, Crc16};
+///
+/// let mut crc = Crc16::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.finish(), crc16(b"123456789"));
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Crc16 {
+    crc: u16,
+}
+
+impl Crc16 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds more data to the CRC
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.crc ^= byte as u16;
+            for _bit in 0..8 {
+                let carry = self.crc & 1;
+                self.crc >>= 1;
+                if carry != 0 {
+                    self.crc ^= 0xA001;
+                }
+            }
+        }
+    }
+
+    /// Returns the CRC of the data fed so far
+    pub fn finish(&self) -> u16 {
+        self.crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc16() {
+        let input = hex::decode(
+            "08010000016B40D9AD80010000000000000000000000000000000103021503010101425E10000001",
+        )
+        .unwrap();
+        assert_eq!(crc16(&input), 0x0000F22A);
+    }
+
+    #[test]
+    fn test_crc16_chunks() {
+        let input = hex::decode(
+            "08010000016B40D9AD80010000000000000000000000000000000103021503010101425E10000001",
+        )
+        .unwrap();
+        let (a, b) = input.split_at(13);
+        assert_eq!(crc16_chunks([a, b]), crc16(&[a, b].concat()));
+        assert_eq!(crc16_chunks([a, b]), 0x0000F22A);
+        assert_eq!(crc16_chunks([]), 0);
+    }
+
+    #[test]
+    fn test_crc16_ccitt() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(CrcAlgorithm::Ccitt.checksum(b"123456789"), 0x29B1);
+        assert_eq!(
+            CrcAlgorithm::default().checksum(b"123456789"),
+            crc16(b"123456789")
+        );
+    }
+}
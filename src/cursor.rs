@@ -0,0 +1,86 @@
+use crate::{
+    parser::{check_crc, tcp_frame_complete_with_options, ParseOptions},
+    TeltonikaFrame,
+};
+
+/// Iterator over the TCP frames of a capture, e.g. a file dump of a connection
+///
+/// A frame that cannot be parsed is skipped by scanning for the next preamble.
+/// Zero runs are common within records, so a preamble is only accepted
+/// when a whole AVL or GPRS frame with a valid CRC starts there.
+///
+/// ```rust
+/// use nom_teltonika::{FrameCursor, TeltonikaFrame};
+///
+/// let capture = [0x00; 8];
+/// let mut cursor = FrameCursor::new(&capture);
+/// assert_eq!(cursor.next(), Some(TeltonikaFrame::KeepAlive));
+/// assert_eq!(cursor.next(), Some(TeltonikaFrame::KeepAlive));
+/// assert_eq!(cursor.next(), None);
+/// assert!(cursor.remaining().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameCursor<'a> {
+    input: &'a [u8],
+    options: ParseOptions,
+    skipped: usize,
+}
+
+impl<'a> FrameCursor<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_options(input, ParseOptions::default())
+    }
+
+    /// Creates a cursor parsing the frames with custom [`ParseOptions`]
+    pub fn with_options(input: &'a [u8], options: ParseOptions) -> Self {
+        Self {
+            input,
+            options,
+            skipped: 0,
+        }
+    }
+
+    /// Bytes not parsed yet, holding a truncated frame once the iteration ends
+    pub fn remaining(&self) -> &'a [u8] {
+        self.input
+    }
+
+    /// Number of bytes skipped so far to resync after frames that could not be parsed
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Position of the next preamble starting a valid AVL or GPRS frame, after the current one
+    ///
+    /// The CRC of each candidate is checked before parsing it, which is much cheaper
+    fn resync(&self) -> Option<usize> {
+        (1..self.input.len()).find(|&start| {
+            let candidate = &self.input[start..];
+            candidate.starts_with(b"\0\0\0\0")
+                && matches!(check_crc(candidate), Ok((_, true)))
+                && matches!(
+                    tcp_frame_complete_with_options(candidate, &self.options),
+                    Ok((_, TeltonikaFrame::AVL(_) | TeltonikaFrame::GPRS(_)))
+                )
+        })
+    }
+}
+
+impl Iterator for FrameCursor<'_> {
+    type Item = TeltonikaFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.input.is_empty() {
+            return None;
+        }
+        if let Ok((rest, frame)) = tcp_frame_complete_with_options(self.input, &self.options) {
+            self.input = rest;
+            return Some(frame);
+        }
+
+        let start = self.resync()?;
+        self.skipped += start;
+        self.input = &self.input[start..];
+        self.next()
+    }
+}
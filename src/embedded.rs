@@ -0,0 +1,41 @@
+use std::io;
+
+use embedded_io::Error;
+
+/// Adapter implementing [`std::io::Read`] and [`std::io::Write`] over an [`embedded_io`] socket,
+/// e.g. a `smoltcp` one, so that it can be wrapped in a [`TeltonikaStream`](crate::TeltonikaStream)
+///
+/// Errors are converted using their [`embedded_io::ErrorKind`].
+///
+/// ```rust,ignore
+/// let mut stream = TeltonikaStream::new(EmbeddedIo(socket));
+/// let frame = stream.read_frame()?;
+/// ```
+#[derive(Debug)]
+pub struct EmbeddedIo<S>(pub S);
+
+impl<S> EmbeddedIo<S> {
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+fn io_error(error: impl Error) -> io::Error {
+    io::Error::new(error.kind().into(), format!("{error:?}"))
+}
+
+impl<S: embedded_io::Read> io::Read for EmbeddedIo<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(io_error)
+    }
+}
+
+impl<S: embedded_io::Write> io::Write for EmbeddedIo<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(io_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().map_err(io_error)
+    }
+}
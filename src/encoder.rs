@@ -0,0 +1,884 @@
+use std::{fmt, io};
+
+use crate::{
+    crc::Crc16, AVLDatagram, AVLEventIO, AVLEventIOValue, AVLFrame, AVLRecord, AVLRecordRaw, Codec,
+    EventGenerationCause,
+};
+
+/// Reason a frame cannot be encoded as it is
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValidationError {
+    /// The codec is not an AVL codec (C8, C8Ext or C16)
+    UnsupportedCodec(Codec),
+    /// More records than the 1 byte count can hold
+    TooManyRecords(usize),
+    /// C16 records must have a generation type, others must not
+    GenerationTypeMismatch { record: usize },
+    /// [`EventGenerationCause::None`] has no wire value
+    UnencodableGenerationType { record: usize },
+    /// An event id does not fit the codec id width (1 byte for C8)
+    EventIdOutOfRange { record: usize, id: u16 },
+    /// More IO events than the codec count width can hold
+    TooManyEvents { record: usize, count: usize },
+    /// Variable length IO values are only supported by C8Ext
+    UnsupportedVariableEvent { record: usize, id: u16 },
+    /// A variable length IO value does not fit its 2 bytes length
+    ValueTooLong { record: usize, id: u16, len: usize },
+    /// More commands than the 1 byte count can hold
+    TooManyCommands(usize),
+    /// A command, prefix included, does not fit the 4 bytes size
+    CommandTooLong { command: usize },
+    /// The data field does not fit the 4 bytes data length of the frame,
+    /// or the datagram the 2 bytes length prefix
+    DataTooLong(usize),
+    /// The degrees of a record do not match its raw coordinates, see [`AVLRecord::set_coordinates`]
+    CoordinatesMismatch { record: usize },
+    /// An IO id does not fit the codec id width, see [`AVLEventIO::new_checked`]
+    IoIdOutOfRange { id: u16, codec: Codec },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedCodec(codec) => write!(f, "{codec:?} is not an AVL codec"),
+            Self::TooManyRecords(count) => write!(f, "{count} records do not fit in a frame"),
+            Self::GenerationTypeMismatch { record } => write!(
+                f,
+                "Record {record} generation type presence does not match the codec"
+            ),
+            Self::UnencodableGenerationType { record } => {
+                write!(f, "Record {record} generation type cannot be encoded")
+            }
+            Self::EventIdOutOfRange { record, id } => {
+                write!(f, "Record {record} event id {id} does not fit the codec")
+            }
+            Self::TooManyEvents { record, count } => {
+                write!(
+                    f,
+                    "Record {record} has {count} IO events, too many for the codec"
+                )
+            }
+            Self::UnsupportedVariableEvent { record, id } => write!(
+                f,
+                "Record {record} IO event {id} has a variable length value, only supported by C8Ext"
+            ),
+            Self::ValueTooLong { record, id, len } => write!(
+                f,
+                "Record {record} IO event {id} has a value of {len} bytes, too long to encode"
+            ),
+            Self::TooManyCommands(count) => write!(f, "{count} commands do not fit in a frame"),
+            Self::CommandTooLong { command } => write!(f, "Command {command} is too long"),
+            Self::DataTooLong(len) => write!(f, "{len} bytes of data do not fit in a frame"),
+            Self::CoordinatesMismatch { record } => write!(
+                f,
+                "Record {record} degrees do not match its raw coordinates"
+            ),
+            Self::IoIdOutOfRange { id, codec } => {
+                write!(f, "IO id {id} does not fit {codec:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Largest event id the codec id width can hold (1 byte for C8)
+fn max_event_id(codec: Codec) -> u16 {
+    if codec == Codec::C8 {
+        u8::MAX as u16
+    } else {
+        u16::MAX
+    }
+}
+
+impl AVLEventIO {
+    /// Creates an IO event whose id fits the id width of `codec`, 1 byte for C8
+    ///
+    /// Encoding checks it anyway, see [`AVLFrame::validate`], this catches the mistake where the event is built.
+    ///
+    /// ```rust
+    /// use nom_teltonika::{AVLEventIO, AVLEventIOValue, Codec, ValidationError};
+    ///
+    /// assert!(AVLEventIO::new_checked(300, AVLEventIOValue::U8(1), Codec::C8Ext).is_ok());
+    /// assert_eq!(
+    ///     AVLEventIO::new_checked(300, AVLEventIOValue::U8(1), Codec::C8),
+    ///     Err(ValidationError::IoIdOutOfRange { id: 300, codec: Codec::C8 })
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if `codec` is not an AVL codec or the id does not fit it.
+    pub fn new_checked(
+        id: u16,
+        value: AVLEventIOValue,
+        codec: Codec,
+    ) -> Result<Self, ValidationError> {
+        if !matches!(codec, Codec::C8 | Codec::C8Ext | Codec::C16) {
+            return Err(ValidationError::UnsupportedCodec(codec));
+        }
+        if id > max_event_id(codec) {
+            return Err(ValidationError::IoIdOutOfRange { id, codec });
+        }
+        Ok(Self { id, value })
+    }
+}
+
+/// Checks the records can be encoded with the codec
+fn validate_records(codec: Codec, records: &[AVLRecord]) -> Result<(), ValidationError> {
+    if !matches!(codec, Codec::C8 | Codec::C8Ext | Codec::C16) {
+        return Err(ValidationError::UnsupportedCodec(codec));
+    }
+    let max_records = (1 << (8 * codec.record_count_width())) - 1;
+    if records.len() > max_records {
+        return Err(ValidationError::TooManyRecords(records.len()));
+    }
+
+    let max_id = max_event_id(codec);
+    let max_count = if codec == Codec::C8Ext {
+        u16::MAX as usize
+    } else {
+        u8::MAX as usize
+    };
+
+    for (record, avl_record) in records.iter().enumerate() {
+        match (codec, avl_record.generation_type) {
+            (Codec::C16, Some(EventGenerationCause::None)) => {
+                return Err(ValidationError::UnencodableGenerationType { record })
+            }
+            (Codec::C16, Some(_)) | (Codec::C8 | Codec::C8Ext, None) => {}
+            _ => return Err(ValidationError::GenerationTypeMismatch { record }),
+        }
+        if !avl_record.coordinates_match() {
+            return Err(ValidationError::CoordinatesMismatch { record });
+        }
+        if avl_record.trigger_event_id > max_id {
+            return Err(ValidationError::EventIdOutOfRange {
+                record,
+                id: avl_record.trigger_event_id,
+            });
+        }
+        if avl_record.io_events.len() > max_count {
+            return Err(ValidationError::TooManyEvents {
+                record,
+                count: avl_record.io_events.len(),
+            });
+        }
+        for event in &avl_record.io_events {
+            if event.id > max_id {
+                return Err(ValidationError::EventIdOutOfRange {
+                    record,
+                    id: event.id,
+                });
+            }
+            if let AVLEventIOValue::Variable(value)
+            | AVLEventIOValue::Parsed { raw: value, .. }
+            | AVLEventIOValue::ParseFailed { raw: value, .. } = &event.value
+            {
+                if codec != Codec::C8Ext {
+                    return Err(ValidationError::UnsupportedVariableEvent {
+                        record,
+                        id: event.id,
+                    });
+                }
+                if value.len() > u16::MAX as usize {
+                    return Err(ValidationError::ValueTooLong {
+                        record,
+                        id: event.id,
+                        len: value.len(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl AVLFrame {
+    /// Checks the frame is consistent with its codec, so that it can be encoded.
+    ///
+    /// Useful for frames not coming from the parser, e.g. built by hand or deserialized.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_records(self.codec, &self.records)
+    }
+
+    /// Encodes the frame as sent over TCP, computing a fresh CRC.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the frame does not pass [`AVLFrame::validate`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ValidationError> {
+        self.encode(None)
+    }
+
+    /// Encodes the frame as sent over TCP, keeping the received [`AVLFrame::crc16`].
+    ///
+    /// A frame parsed with the default [`ParseOptions`](crate::parser::ParseOptions) is encoded back
+    /// to the exact bytes it was parsed from, e.g. to relay it untouched:
+    /// records keep their raw coordinates, IO events their order and variable values their raw bytes.
+    /// Options changing the records, such as dropping padding IO events, break this.
+    ///
+    /// The CRC is not checked, it is stale for frames whose records changed, see [`AVLFrame::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the frame does not pass [`AVLFrame::validate`].
+    pub fn to_bytes_exact(&self) -> Result<Vec<u8>, ValidationError> {
+        self.encode(Some(self.crc16))
+    }
+
+    /// Writes the frame as encoded by [`AVLFrame::to_bytes`], one record at a time
+    ///
+    /// Only a record is held in memory at once, the CRC is computed while writing.
+    /// Nothing is flushed, wrap `writer` in a [`std::io::BufWriter`] when it is unbuffered.
+    ///
+    /// # Errors
+    ///
+    /// If the frame does not pass [`AVLFrame::validate`], an error kind of
+    /// [`std::io::ErrorKind::InvalidInput`] is returned before writing anything.
+    /// Errors of `writer` are returned as they are.
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let data_len = data_len(self.codec, &self.records);
+        writer.write_all(&[0x00, 0x00, 0x00, 0x00])?; // preamble
+        writer.write_all(&(data_len as u32).to_be_bytes())?;
+
+        let mut crc = Crc16::new();
+        let mut write_data = |bytes: &[u8]| {
+            crc.update(bytes);
+            writer.write_all(bytes)
+        };
+        let mut buffer = vec![self.codec.into()];
+        encode_record_count(self.codec, self.records.len(), &mut buffer);
+        write_data(&buffer)?;
+        for record in &self.records {
+            buffer.clear();
+            encode_record(self.codec, record, &mut buffer);
+            write_data(&buffer)?;
+        }
+        buffer.clear();
+        encode_record_count(self.codec, self.records.len(), &mut buffer);
+        write_data(&buffer)?;
+
+        writer.write_all(&(crc.finish() as u32).to_be_bytes())
+    }
+
+    /// Encodes the frame with the given CRC, computing it when `None`
+    fn encode(&self, crc16: Option<u32>) -> Result<Vec<u8>, ValidationError> {
+        self.validate()?;
+
+        let mut data = vec![self.codec.into()];
+        encode_record_count(self.codec, self.records.len(), &mut data);
+        for record in &self.records {
+            encode_record(self.codec, record, &mut data);
+        }
+        encode_record_count(self.codec, self.records.len(), &mut data);
+
+        let crc16 = crc16.unwrap_or_else(|| crate::crc16(&data) as u32);
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.extend([0x00, 0x00, 0x00, 0x00]); // preamble
+        bytes.extend((data.len() as u32).to_be_bytes());
+        bytes.extend(&data);
+        bytes.extend(crc16.to_be_bytes());
+        Ok(bytes)
+    }
+
+    /// Length of the bytes [`AVLFrame::to_bytes`] produces, without encoding the frame.
+    ///
+    /// Meaningful only for frames passing [`AVLFrame::validate`].
+    pub fn encoded_len(&self) -> usize {
+        4 + // preamble
+        4 + // data length
+        data_len(self.codec, &self.records) +
+        4 // CRC 16
+    }
+
+    /// Whether the server ACK accepts the frame, that is it matches the number of records.
+    ///
+    /// Devices resend the frame otherwise.
+    pub fn is_ack_accepted(&self, ack: u32) -> bool {
+        ack as usize == self.records.len()
+    }
+}
+
+impl AVLDatagram {
+    /// Checks the datagram is consistent with its codec and fits its length prefix, see [`AVLFrame::validate`].
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_records(self.codec, &self.records)?;
+        let len = self.encoded_len() - 2;
+        if len > u16::MAX as usize {
+            return Err(ValidationError::DataTooLong(len));
+        }
+        Ok(())
+    }
+
+    /// Encodes the datagram as sent over UDP, length prefix included.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the datagram does not pass [`AVLDatagram::validate`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ValidationError> {
+        self.validate()?;
+
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.extend(((self.encoded_len() - 2) as u16).to_be_bytes());
+        bytes.extend(self.packet_id.to_be_bytes());
+        bytes.push(0x01); // non usable byte
+        bytes.push(self.avl_packet_id);
+        bytes.extend((self.imei.as_str().len() as u16).to_be_bytes());
+        bytes.extend(self.imei.as_str().as_bytes());
+        bytes.push(self.codec.into());
+        encode_record_count(self.codec, self.records.len(), &mut bytes);
+        for record in &self.records {
+            encode_record(self.codec, record, &mut bytes);
+        }
+        encode_record_count(self.codec, self.records.len(), &mut bytes);
+        Ok(bytes)
+    }
+
+    /// Length of the datagram once encoded, length prefix included.
+    ///
+    /// Meaningful only for datagrams passing [`AVLDatagram::validate`].
+    pub fn encoded_len(&self) -> usize {
+        2 + // length
+        2 + // packet id
+        1 + // non usable byte
+        1 + // avl packet id
+        2 + crate::IMEI_LENGTH + // imei length + imei
+        data_len(self.codec, &self.records)
+    }
+}
+
+/// Appends the record count as wide as the codec wants it, see [`Codec::record_count_width`]
+pub(crate) fn encode_record_count(codec: Codec, count: usize, bytes: &mut Vec<u8>) {
+    let count = (count as u64).to_be_bytes();
+    bytes.extend(&count[count.len() - codec.record_count_width()..]);
+}
+
+/// Length of the codec, the records and their counts
+fn data_len(codec: Codec, records: &[AVLRecord]) -> usize {
+    1 + // codec
+    codec.record_count_width() + // number of records
+    records.iter().map(|record| record_len(codec, record)).sum::<usize>() +
+    codec.record_count_width() // number of records
+}
+
+/// Length of a record once encoded, see [`encode_record`]
+fn record_len(codec: Codec, record: &AVLRecord) -> usize {
+    let (id_len, count_len) = match codec {
+        Codec::C8 => (1, 1),
+        Codec::C8Ext => (2, 2),
+        _ => (2, 1),
+    };
+    let groups = if codec == Codec::C8Ext { 5 } else { 4 };
+
+    8 + // timestamp
+    1 + // priority
+    4 + 4 + // longitude, latitude
+    2 + 2 + // altitude, angle
+    1 + // satellites
+    2 + // speed
+    id_len + // event id
+    record.generation_type.map_or(0, |_| 1) +
+    count_len + // io count
+    groups * count_len + // io count of each group
+    record
+        .io_events
+        .iter()
+        .map(|event| {
+            id_len
+                + match &event.value {
+                    AVLEventIOValue::U8(_) => 1,
+                    AVLEventIOValue::U16(_) => 2,
+                    AVLEventIOValue::U32(_) => 4,
+                    AVLEventIOValue::U64(_) => 8,
+                    AVLEventIOValue::Variable(value)
+                    | AVLEventIOValue::Parsed { raw: value, .. }
+| AVLEventIOValue::ParseFailed { raw: value, .. } => 2 + value.len(),
+                }
+        })
+        .sum::<usize>()
+}
+
+/// Writes an event id or count with the codec width
+fn encode_width(codec: Codec, value: u16, wide: bool, buffer: &mut Vec<u8>) {
+    if wide || codec == Codec::C8Ext {
+        buffer.extend(value.to_be_bytes());
+    } else {
+        buffer.push(value as u8);
+    }
+}
+
+fn encode_id(codec: Codec, id: u16, buffer: &mut Vec<u8>) {
+    encode_width(codec, id, codec == Codec::C16, buffer)
+}
+
+fn encode_count(codec: Codec, count: usize, buffer: &mut Vec<u8>) {
+    encode_width(codec, count as u16, false, buffer)
+}
+
+/// Writes a record, IO events are grouped by value width as the protocol requires
+fn encode_record(codec: Codec, record: &AVLRecord, buffer: &mut Vec<u8>) {
+    buffer.extend((record.timestamp.timestamp_millis() as u64).to_be_bytes());
+    buffer.push(record.priority.into());
+    buffer.extend(record.longitude_raw.to_be_bytes());
+    buffer.extend(record.latitude_raw.to_be_bytes());
+    buffer.extend(record.altitude.to_be_bytes());
+    buffer.extend(record.angle.to_be_bytes());
+    buffer.push(record.satellites);
+    buffer.extend(record.speed.to_be_bytes());
+
+    encode_id(codec, record.trigger_event_id, buffer);
+    if let Some(generation_type) = record.generation_type {
+        buffer.push(generation_type.into());
+    }
+    encode_count(codec, record.io_events.len(), buffer);
+
+    type Group = fn(&AVLEventIOValue) -> Option<Vec<u8>>;
+    let mut groups: Vec<Group> = vec![
+        |value| match value {
+            AVLEventIOValue::U8(value) => Some(vec![*value]),
+            _ => None,
+        },
+        |value| match value {
+            AVLEventIOValue::U16(value) => Some(value.to_be_bytes().to_vec()),
+            _ => None,
+        },
+        |value| match value {
+            AVLEventIOValue::U32(value) => Some(value.to_be_bytes().to_vec()),
+            _ => None,
+        },
+        |value| match value {
+            AVLEventIOValue::U64(value) => Some(value.to_be_bytes().to_vec()),
+            _ => None,
+        },
+    ];
+    if codec == Codec::C8Ext {
+        groups.push(|value| match value {
+            AVLEventIOValue::Variable(value)
+            | AVLEventIOValue::Parsed { raw: value, .. }
+            | AVLEventIOValue::ParseFailed { raw: value, .. } => {
+                let mut bytes = (value.len() as u16).to_be_bytes().to_vec();
+                bytes.extend(value);
+                Some(bytes)
+            }
+            _ => None,
+        });
+    }
+
+    for group in groups {
+        let events: Vec<(&AVLEventIO, Vec<u8>)> = record
+            .io_events
+            .iter()
+            .filter_map(|event| group(&event.value).map(|value| (event, value)))
+            .collect();
+        encode_count(codec, events.len(), buffer);
+        for (event, value) in events {
+            encode_id(codec, event.id, buffer);
+            buffer.extend(value);
+        }
+    }
+}
+
+impl AVLRecordRaw {
+    /// Encodes the record as parsed by [`crate::parser::record_raw`] with the same codec.
+    ///
+    /// Ids and counts are truncated to the codec width.
+    pub fn to_bytes(&self, codec: Codec) -> Vec<u8> {
+        let mut buffer = vec![];
+        buffer.extend(self.timestamp.to_be_bytes());
+        buffer.push(self.priority.into());
+        buffer.extend(self.longitude.to_be_bytes());
+        buffer.extend(self.latitude.to_be_bytes());
+        buffer.extend(self.altitude.to_be_bytes());
+        buffer.extend(self.angle.to_be_bytes());
+        buffer.push(self.satellites);
+        buffer.extend(self.speed.to_be_bytes());
+
+        encode_id(codec, self.trigger_event_id, &mut buffer);
+        if let Some(generation_type) = self.generation_type {
+            buffer.push(generation_type.into());
+        }
+        encode_count(codec, self.io_count as usize, &mut buffer);
+
+        fn group<T>(
+            codec: Codec,
+            events: &[(u16, T)],
+            value: impl Fn(&T) -> Vec<u8>,
+            buffer: &mut Vec<u8>,
+        ) {
+            encode_count(codec, events.len(), buffer);
+            for (id, event) in events {
+                encode_id(codec, *id, buffer);
+                buffer.extend(value(event));
+            }
+        }
+        group(codec, &self.io_u8, |value| vec![*value], &mut buffer);
+        group(
+            codec,
+            &self.io_u16,
+            |value| value.to_be_bytes().to_vec(),
+            &mut buffer,
+        );
+        group(
+            codec,
+            &self.io_u32,
+            |value| value.to_be_bytes().to_vec(),
+            &mut buffer,
+        );
+        group(
+            codec,
+            &self.io_u64,
+            |value| value.to_be_bytes().to_vec(),
+            &mut buffer,
+        );
+        if let Some(io_variable) = &self.io_variable {
+            group(
+                codec,
+                io_variable,
+                |value| {
+                    let mut bytes = vec![];
+                    encode_count(codec, value.len(), &mut bytes);
+                    bytes.extend(value);
+                    bytes
+                },
+                &mut buffer,
+            );
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::parser::tcp_frame;
+
+    fn round_trip(input: &str) {
+        let input = hex::decode(input).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let frame = frame.unwrap_avl();
+        assert_eq!(frame.to_bytes().unwrap(), input);
+        assert_eq!(frame.encoded_len(), input.len());
+    }
+
+    #[test]
+    fn encode_frame_codec8() {
+        round_trip(CODEC8_FRAME_1);
+    }
+
+    #[test]
+    fn encode_frame_codec8ext() {
+        round_trip(CODEC8EXT_FRAME);
+    }
+
+    #[test]
+    fn encode_frame_codec16() {
+        round_trip(CODEC16_FRAME);
+    }
+
+    #[test]
+    fn encode_fixtures_exactly() {
+        for fixture in AVL_FIXTURES {
+            let input = hex::decode(fixture).unwrap();
+            let (_, frame) = tcp_frame(&input).unwrap();
+            assert_eq!(frame.unwrap_avl().to_bytes_exact().unwrap(), input);
+        }
+
+        let input = include_bytes!("../tests/test.bin");
+        let (_, frame) = tcp_frame(input).unwrap();
+        let frame = frame.unwrap_avl();
+        let bytes = frame.to_bytes_exact().unwrap();
+        assert_eq!(bytes, input[..bytes.len()]);
+
+        // The received CRC is kept even when wrong
+        let mut frame = frame;
+        frame.crc16 = 0xDEADBEEF;
+        let bytes = frame.to_bytes_exact().unwrap();
+        assert_eq!(bytes[bytes.len() - 4..], [0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[test]
+    fn validate_inconsistent_frame() {
+        let input = hex::decode(CODEC8_FRAME_1).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut frame = frame.unwrap_avl();
+        // Hand edited as C16 without generation types
+        frame.codec = Codec::C16;
+
+        assert_eq!(
+            frame.validate(),
+            Err(ValidationError::GenerationTypeMismatch { record: 0 })
+        );
+        assert_eq!(
+            frame.to_bytes(),
+            Err(ValidationError::GenerationTypeMismatch { record: 0 })
+        );
+
+        frame.codec = Codec::C12;
+        assert_eq!(
+            frame.validate(),
+            Err(ValidationError::UnsupportedCodec(Codec::C12))
+        );
+    }
+
+    #[test]
+    fn validate_event_id_width() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut frame = frame.unwrap_avl();
+        frame.records[0].io_events[0].id = 0x0100;
+        assert!(frame.validate().is_ok());
+
+        frame.codec = Codec::C8;
+        assert_eq!(
+            frame.validate(),
+            Err(ValidationError::EventIdOutOfRange {
+                record: 0,
+                id: 0x0100
+            })
+        );
+    }
+
+    #[test]
+    fn new_checked_event() {
+        assert_eq!(
+            AVLEventIO::new_checked(300, AVLEventIOValue::U16(1), Codec::C8),
+            Err(ValidationError::IoIdOutOfRange {
+                id: 300,
+                codec: Codec::C8
+            })
+        );
+        assert_eq!(
+            AVLEventIO::new_checked(255, AVLEventIOValue::U16(1), Codec::C8),
+            Ok(AVLEventIO {
+                id: 255,
+                value: AVLEventIOValue::U16(1)
+            })
+        );
+        assert!(AVLEventIO::new_checked(300, AVLEventIOValue::U16(1), Codec::C16).is_ok());
+        assert_eq!(
+            AVLEventIO::new_checked(1, AVLEventIOValue::U16(1), Codec::C12),
+            Err(ValidationError::UnsupportedCodec(Codec::C12))
+        );
+    }
+
+    #[test]
+    fn write_frame_to_writer() {
+        let input = include_bytes!("../tests/test.bin");
+        let (_, frame) = tcp_frame(input).unwrap();
+        let frame = frame.unwrap_avl();
+
+        let mut bytes = vec![];
+        frame.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, frame.to_bytes().unwrap());
+        let (rest, written) = tcp_frame(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(written.unwrap_avl(), frame);
+
+        let mut frame = frame;
+        frame.codec = Codec::C12;
+        let mut bytes = vec![];
+        let error = frame.write_to(&mut bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn encode_maximum_records() {
+        let input = hex::decode(CODEC8_FRAME_1).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut frame = frame.unwrap_avl();
+        let record = frame.records[0].clone();
+
+        // Every AVL codec counts the records with a single byte
+        frame.records = vec![record.clone(); 255];
+        let bytes = frame.to_bytes().unwrap();
+        let (rest, parsed) = tcp_frame(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(parsed.record_count(), 255);
+        assert!(parsed.unwrap_avl().payload_eq(&frame));
+
+        frame.records.push(record);
+        for codec in [Codec::C8, Codec::C8Ext, Codec::C16] {
+            frame.codec = codec;
+            assert_eq!(frame.validate(), Err(ValidationError::TooManyRecords(256)));
+        }
+    }
+
+    #[test]
+    fn encode_extended_frame() {
+        let input = hex::decode(CODEC8_FRAME_1).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let record = frame.unwrap_avl().records.remove(0);
+
+        let mut frame = AVLFrame {
+            codec: Codec::C8,
+            records: vec![],
+            crc16: 0,
+        };
+        frame.push_record(record.clone());
+        frame.extend(vec![record.clone(), record]);
+        assert_eq!(frame.records.len(), 3);
+        // Stale until encoded
+        assert_eq!(frame.crc16, 0);
+
+        let bytes = frame.to_bytes().unwrap();
+        let (rest, encoded) = tcp_frame(&bytes).unwrap();
+        assert!(rest.is_empty());
+        let encoded = encoded.unwrap_avl();
+        assert!(encoded.payload_eq(&frame));
+        assert_ne!(encoded.crc16, 0);
+    }
+
+    #[test]
+    fn encoded_len_datagram() {
+        let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+        let (_, datagram) = crate::parser::udp_datagram(&input).unwrap();
+        assert_eq!(datagram.encoded_len(), input.len());
+    }
+
+    #[test]
+    fn encode_mutated_records() {
+        let input = hex::decode(CODEC8_FRAME_3).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut frame = frame.unwrap_avl();
+
+        for record in frame.records_iter_mut() {
+            record.altitude = 120;
+            record.speed = 42;
+        }
+        let stale_crc = frame.crc16;
+
+        let bytes = frame.to_bytes().unwrap();
+        let (_, encoded) = tcp_frame(&bytes).unwrap();
+        let encoded = encoded.unwrap_avl();
+        assert_ne!(encoded.crc16, stale_crc);
+        assert_eq!(encoded.records, frame.records);
+    }
+
+    #[test]
+    fn encode_snapped_coordinates() {
+        let input = hex::decode(CODEC8_FRAME_3).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut frame = frame.unwrap_avl();
+
+        // Degrees alone are not encoded
+        frame.records[1].longitude = 45.0;
+        assert_eq!(
+            frame.validate(),
+            Err(ValidationError::CoordinatesMismatch { record: 1 })
+        );
+
+        frame.records[1].set_coordinates(45.0, -12.345_678_91);
+        assert_eq!(frame.records[1].longitude_raw, 450_000_000);
+        assert_eq!(frame.records[1].latitude_raw, -123_456_789);
+        let (_, encoded) = tcp_frame(&frame.to_bytes().unwrap()).unwrap();
+        let encoded = encoded.unwrap_avl();
+        assert_eq!(encoded.records[1].longitude, 45.0);
+        assert_eq!(encoded.records[1].latitude, -12.345_678_9);
+    }
+
+    #[test]
+    fn record_count_width() {
+        for codec in Codec::all() {
+            assert_eq!(codec.record_count_width(), 1);
+        }
+
+        let frame = AVLFrame {
+            codec: Codec::C8Ext,
+            records: vec![],
+            crc16: 0,
+        };
+        let bytes = frame.to_bytes().unwrap();
+        assert_eq!(bytes.len(), frame.encoded_len());
+        assert_eq!(&bytes[8..11], [0x8E, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn encode_datagram() {
+        let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+        let (_, datagram) = crate::parser::udp_datagram(&input).unwrap();
+        let bytes = datagram.to_bytes().unwrap();
+        assert_eq!(bytes, input);
+
+        let (rest, reparsed) = crate::parser::udp_datagram(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(reparsed, datagram);
+    }
+
+    #[test]
+    fn encode_value_too_long() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut frame = frame.unwrap_avl();
+        frame.records[0].io_events.push(AVLEventIO {
+            id: 0x0100,
+            value: AVLEventIOValue::Variable(vec![0xAB; u16::MAX as usize]),
+        });
+        assert_eq!(frame.validate(), Ok(()));
+
+        frame.records[0].io_events[5].value = AVLEventIOValue::Variable(vec![0xAB; 70_000]);
+        assert_eq!(
+            frame.validate(),
+            Err(ValidationError::ValueTooLong {
+                record: 0,
+                id: 0x0100,
+                len: 70_000
+            })
+        );
+    }
+
+    #[test]
+    fn encode_datagram_too_long() {
+        let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+        let (_, mut datagram) = crate::parser::udp_datagram(&input).unwrap();
+        datagram.codec = Codec::C8Ext;
+        datagram.records[0].io_events.push(AVLEventIO {
+            id: 0x0100,
+            value: AVLEventIOValue::Variable(vec![0xAB; 40_000]),
+        });
+        let record = datagram.records[0].clone();
+        assert_eq!(datagram.validate(), Ok(()));
+
+        // Past the 2 bytes length prefix
+        datagram.records.push(record);
+        let len = datagram.encoded_len() - 2;
+        assert!(len > u16::MAX as usize);
+        assert_eq!(datagram.validate(), Err(ValidationError::DataTooLong(len)));
+        assert_eq!(datagram.to_bytes(), Err(ValidationError::DataTooLong(len)));
+    }
+
+    #[test]
+    fn raw_record_round_trip() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        // Between the number of records and the CRC
+        let record = &input[10..input.len() - 5];
+        let (rest, raw) = crate::parser::record_raw(Codec::C8Ext)(record).unwrap();
+
+        assert!(rest.is_empty());
+        assert_eq!(raw.io_count, 5);
+        assert_eq!(raw.io_u8, vec![(0x0001, 0x01)]);
+        assert_eq!(raw.io_variable, Some(vec![]));
+        assert_eq!(raw.to_bytes(Codec::C8Ext), record);
+    }
+
+    #[test]
+    fn payload_eq_ignores_crc() {
+        let input = hex::decode(CODEC8_FRAME_1).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let frame = frame.unwrap_avl();
+
+        let (_, reparsed) = tcp_frame(&frame.to_bytes().unwrap()).unwrap();
+        let mut reparsed = reparsed.unwrap_avl();
+        reparsed.crc16 = 0;
+        assert_ne!(frame, reparsed);
+        assert!(frame.payload_eq(&reparsed));
+
+        reparsed.records[0].speed += 1;
+        assert!(!frame.payload_eq(&reparsed));
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::{TeltonikaFrame, UnknownCodec12Type};
+
+/// Owned error of the parsers meant for complete inputs, see [`crate::parser::tcp_frame_complete`]
+#[derive(Debug, PartialEq, Clone)]
+pub enum TeltonikaParseError {
+    /// The input ends before the end of the frame
+    Truncated {
+        /// Length of the frame declared by its header, or of the header when it is incomplete too
+        declared: usize,
+        /// Length of the input
+        available: usize,
+    },
+    /// The codec is unknown, or one that is not parsed such as Codec 13
+    UnsupportedCodec {
+        /// Raw codec byte
+        codec: u8,
+        /// Length of the frame declared by its header
+        declared: usize,
+    },
+    /// The type of a GPRS frame is neither a command nor a response
+    UnknownCodec12Type(UnknownCodec12Type),
+    /// The frame declares a data length of 0
+    EmptyData,
+    /// The input holds a GPRS frame or a keep-alive where an AVL frame was expected
+    NotAvl,
+    /// The frame is well formed but its CRC does not match its data
+    CrcMismatch {
+        /// The frame parsed regardless of the CRC, holding the received one
+        frame: TeltonikaFrame,
+        /// CRC calculated on the received data
+        calculated: u16,
+    },
+    /// The input is malformed
+    Invalid {
+        kind: nom::error::ErrorKind,
+        /// Position in the input where parsing failed
+        offset: usize,
+    },
+    /// The input of a streaming parser ends early, more bytes are needed
+    Incomplete(nom::Needed),
+    /// Failure of a streaming parser converted without its input, see [`TeltonikaParseError::from_nom`]
+    ///
+    /// ```rust
+    /// use nom_teltonika::{parser, TeltonikaParseError};
+    ///
+    /// fn imei(input: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    ///     let (_, imei) = parser::imei(input).map_err(TeltonikaParseError::from)?;
+    ///     Ok(imei.to_string())
+    /// }
+    /// assert!(imei(&[0x00, 0x0F]).is_err());
+    /// ```
+    Streaming {
+        kind: nom::error::ErrorKind,
+        /// Number of bytes left in the input where parsing failed
+        remaining: usize,
+    },
+}
+
+impl TeltonikaParseError {
+    /// Converts the error a streaming parser returned on `input`,
+    /// failures are reported as [`TeltonikaParseError::Invalid`] at their offset in `input`
+    pub fn from_nom(input: &[u8], error: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        match error.into() {
+            Self::Streaming { kind, remaining } => Self::Invalid {
+                kind,
+                offset: input.len().saturating_sub(remaining),
+            },
+            error => error,
+        }
+    }
+}
+
+impl From<nom::Err<nom::error::Error<&[u8]>>> for TeltonikaParseError {
+    fn from(error: nom::Err<nom::error::Error<&[u8]>>) -> Self {
+        match error {
+            nom::Err::Incomplete(needed) => Self::Incomplete(needed),
+            nom::Err::Error(e) | nom::Err::Failure(e) => Self::Streaming {
+                kind: e.code,
+                remaining: e.input.len(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TeltonikaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated {
+                declared,
+                available,
+            } => write!(
+                f,
+                "Frame truncated: {declared} bytes declared, {available} available"
+            ),
+            Self::UnsupportedCodec { codec, declared } => write!(
+                f,
+                "Unsupported codec {codec:#04X} in a frame of {declared} bytes"
+            ),
+            Self::UnknownCodec12Type(e) => e.fmt(f),
+            Self::EmptyData => write!(f, "Frame with an empty data field"),
+            Self::NotAvl => write!(f, "Frame is not AVL"),
+            Self::CrcMismatch { frame, calculated } => {
+                let received = match frame {
+                    TeltonikaFrame::AVL(frame) => frame.crc16,
+                    TeltonikaFrame::GPRS(frame) => frame.crc16,
+                    TeltonikaFrame::KeepAlive => 0,
+                };
+                write!(
+                    f,
+                    "Frame CRC mismatch: received {received:#06X}, calculated {calculated:#06X}"
+                )
+            }
+            Self::Invalid { kind, offset } => {
+                write!(f, "Invalid frame at byte {offset}: {}", kind.description())
+            }
+            Self::Incomplete(nom::Needed::Size(needed)) => {
+                write!(f, "Incomplete input: {needed} more bytes needed")
+            }
+            Self::Incomplete(nom::Needed::Unknown) => write!(f, "Incomplete input"),
+            Self::Streaming { kind, remaining } => write!(
+                f,
+                "Invalid input {remaining} bytes before its end: {}",
+                kind.description()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TeltonikaParseError {}
+
+/// Error of [`crate::AVLFrame::from_hex`]
+#[derive(Debug, PartialEq, Clone)]
+pub enum FromHexError {
+    /// A character other than whitespace is not a hex digit, `index` is its byte offset in the string
+    InvalidCharacter { c: char, index: usize },
+    /// The hex digits do not make whole bytes
+    OddLength,
+    /// The decoded bytes are not a valid AVL frame
+    Parse(TeltonikaParseError),
+}
+
+impl fmt::Display for FromHexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCharacter { c, index } => {
+                write!(f, "Invalid hex character {c:?} at {index}")
+            }
+            Self::OddLength => write!(f, "Odd number of hex digits"),
+            Self::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FromHexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidCharacter { .. } | Self::OddLength => None,
+            Self::Parse(e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Hex AVL frames shared by the unit tests
+
+/// Codec 8 record with an event IO id and no IO events
+pub(crate) const BARE_EVENT_FRAME: &str =
+    "000000000000002108010000016B40D9AD8001000000000000000000000000000000F0000000000001000087C3";
+
+/// Codec 8 record whose speed and angle are swapped
+pub(crate) const SWAPPED_SPEED_ANGLE_FRAME: &str = "000000000000002308010000016B40D9AD80010B4E1F801A2B3C4D006400320700B4EF0101EF010000000100002FB3";
+
+/// Second Codec 8 example of the specification
+pub(crate) const CODEC8_FRAME_2: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+
+/// Codec 8 record with a negative altitude
+pub(crate) const NEGATIVE_ALTITUDE_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000FFE200000000000103021503010101425E10000001000066DE";
+
+/// Codec 8 record with the same IO id in two groups
+pub(crate) const DUPLICATE_IO_FRAME: &str = "000000000000002A08010000016B40D9AD80010000000000000000000000000000000004034201EF00EF0101422F3A0000010000A032";
+
+/// Codec 8 record with the GNSS IO events
+pub(crate) const GPS_METRICS_FRAME: &str = "000000000000002E08010000016B40D9AD80010000000000000000000000000900000105021503010103425E10B5000FB600090000010000B226";
+
+/// Codec 8 Extended record with IO events of id 0
+pub(crate) const PADDING_IO_FRAME: &str = "000000000000002F8E010000016B40D9AD800100000000000000000000000000000000EF0002000200000000EF010000000000000000010000E625";
+
+/// First Codec 8 example of the specification
+pub(crate) const CODEC8_FRAME_1: &str = "000000000000003608010000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E0000000000000000010000C7CF";
+
+/// Third Codec 8 example of the specification, with two records
+pub(crate) const CODEC8_FRAME_3: &str = "000000000000004308020000016B40D57B480100000000000000000000000000000001010101000000000000016B40D5C198010000000000000000000000000000000101010101000000020000252C";
+
+/// Codec 8 record in the southern and western hemispheres
+pub(crate) const NEGATIVE_COORDINATES_FRAME: &str = "00000000000000460801000001776D58189001FA0A1F00F1194D80009C009D05000F9B0D06EF01F0001505C80045019B0105B5000BB6000A424257430F8044000002F1000060191000000BE1000100006E2B";
+
+/// Codec 8 Extended example of the specification
+pub(crate) const CODEC8EXT_FRAME: &str = "000000000000004A8E010000016B412CEE000100000000000000000000000000000000010005000100010100010011001D00010010015E2C880002000B000000003544C87A000E000000001DD7E06A00000100002994";
+
+/// Codec 16 example of the specification
+pub(crate) const CODEC16_FRAME: &str = "000000000000005F10020000016BDBC7833000000000000000000000000000000000000B05040200010000030002000B00270042563A00000000016BDBC7871800000000000000000000000000000000000B05040200010000030002000B00260042563A00000200005FB3";
+
+/// Every AVL frame above, all valid
+pub(crate) const AVL_FIXTURES: [&str; 12] = [
+    BARE_EVENT_FRAME,
+    SWAPPED_SPEED_ANGLE_FRAME,
+    CODEC8_FRAME_2,
+    NEGATIVE_ALTITUDE_FRAME,
+    DUPLICATE_IO_FRAME,
+    GPS_METRICS_FRAME,
+    PADDING_IO_FRAME,
+    CODEC8_FRAME_1,
+    CODEC8_FRAME_3,
+    NEGATIVE_COORDINATES_FRAME,
+    CODEC8EXT_FRAME,
+    CODEC16_FRAME,
+];
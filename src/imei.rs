@@ -0,0 +1,247 @@
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of digits of an IMEI
+pub const IMEI_LENGTH: usize = 15;
+
+/// Encoding of the IMEI sent by a device when connecting
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum ImeiFormat {
+    /// `u16` length followed by as many ASCII digits, as the specification requires
+    #[default]
+    LengthPrefixedAscii,
+    /// 8 bytes of packed digits without length, see [`Imei::from_bcd`]
+    Bcd,
+}
+
+/// International Mobile Equipment Identity of a device
+///
+/// Can only be built from exactly 15 ASCII digits, see [`Imei::parse`]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Imei(String);
+
+impl Imei {
+    /// Validates and wraps an IMEI.
+    ///
+    /// The Luhn check digit is not verified, use [`Imei::parse_luhn`] for that.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the IMEI is not made of exactly 15 ASCII digits.
+    pub fn parse(imei: &str) -> Result<Self, ImeiError> {
+        if let Some(position) = imei.chars().position(|c| !c.is_ascii_digit()) {
+            return Err(ImeiError::InvalidDigit(position));
+        }
+        if imei.len() != IMEI_LENGTH {
+            return Err(ImeiError::InvalidLength(imei.len()));
+        }
+        Ok(Self(imei.to_owned()))
+    }
+
+    /// Same as [`Imei::parse`] but also verifies the Luhn check digit.
+    pub fn parse_luhn(imei: &str) -> Result<Self, ImeiError> {
+        let imei = Self::parse(imei)?;
+        if !imei.is_luhn_valid() {
+            return Err(ImeiError::InvalidCheckDigit);
+        }
+        Ok(imei)
+    }
+
+    /// Verifies the last digit against the Luhn checksum of the others.
+    pub fn is_luhn_valid(&self) -> bool {
+        let sum: u32 = self
+            .0
+            .bytes()
+            .rev()
+            .map(|digit| (digit - b'0') as u32)
+            .enumerate()
+            .map(|(i, digit)| match (i % 2 == 1, digit * 2) {
+                (true, doubled) if doubled > 9 => doubled - 9,
+                (true, doubled) => doubled,
+                (false, _) => digit,
+            })
+            .sum();
+        sum % 10 == 0
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Packs the digits two per byte, padded with a leading zero, as sent by Codec 14.
+    pub fn to_bcd(&self) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        // The leading padding digit is the high nibble of the first byte
+        for (i, digit) in self.0.bytes().enumerate() {
+            let nibble = i + 1;
+            bytes[nibble / 2] |= (digit - b'0') << if nibble % 2 == 0 { 4 } else { 0 };
+        }
+        bytes
+    }
+
+    /// Unpacks the digits sent by Codec 14, see [`Imei::to_bcd`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the padding or any digit is not a decimal digit.
+    pub fn from_bcd(bytes: &[u8; 8]) -> Result<Self, ImeiError> {
+        let nibbles = bytes.iter().flat_map(|byte| [byte >> 4, byte & 0x0F]);
+        let mut digits = String::with_capacity(IMEI_LENGTH);
+        for (i, nibble) in nibbles.enumerate() {
+            if nibble > 9 {
+                // The padding digit has no position in the IMEI
+                return Err(ImeiError::InvalidDigit(i.saturating_sub(1)));
+            }
+            if i > 0 {
+                digits.push((b'0' + nibble) as char);
+            }
+        }
+        Self::parse(&digits)
+    }
+
+    /// Returns the IMEI as a plain [`String`].
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for Imei {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Imei {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Imei {
+    type Err = ImeiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Imei {
+    type Error = ImeiError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl TryFrom<&str> for Imei {
+    type Error = ImeiError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::parse(value)
+    }
+}
+
+impl From<Imei> for String {
+    fn from(value: Imei) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq<str> for Imei {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Imei {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Reason an [`Imei`] could not be built
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ImeiError {
+    /// The IMEI is not 15 digits long, holds the actual length
+    InvalidLength(usize),
+    /// A character is not an ASCII digit, holds its position
+    InvalidDigit(usize),
+    /// The Luhn check digit does not match
+    InvalidCheckDigit,
+}
+
+impl fmt::Display for ImeiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength(length) => {
+                write!(f, "IMEI must be {IMEI_LENGTH} digits long, got {length}")
+            }
+            Self::InvalidDigit(position) => write!(f, "IMEI has a non digit at {position}"),
+            Self::InvalidCheckDigit => f.write_str("IMEI check digit does not match"),
+        }
+    }
+}
+
+impl std::error::Error for ImeiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_valid_imei() {
+        let imei = Imei::parse("356307042441013").unwrap();
+        assert_eq!(imei, "356307042441013");
+        assert_eq!(imei.to_string(), "356307042441013");
+        assert_eq!(imei.as_ref(), "356307042441013");
+        assert_eq!(imei.into_string(), String::from("356307042441013"));
+    }
+
+    #[test]
+    fn parse_invalid_imei() {
+        assert_eq!(
+            Imei::parse("35630704244101"),
+            Err(ImeiError::InvalidLength(14))
+        );
+        assert_eq!(
+            Imei::parse("3563070424410133"),
+            Err(ImeiError::InvalidLength(16))
+        );
+        assert_eq!(
+            Imei::parse("35630704244101A"),
+            Err(ImeiError::InvalidDigit(14))
+        );
+        assert_eq!(Imei::parse(""), Err(ImeiError::InvalidLength(0)));
+    }
+
+    #[test]
+    fn imei_to_bcd() {
+        let imei = Imei::parse("352093081452251").unwrap();
+        assert_eq!(
+            imei.to_bcd(),
+            [0x03, 0x52, 0x09, 0x30, 0x81, 0x45, 0x22, 0x51]
+        );
+        assert_eq!(Imei::from_bcd(&imei.to_bcd()), Ok(imei));
+        assert_eq!(
+            Imei::from_bcd(&[0x03, 0x52, 0x09, 0x3A, 0x81, 0x45, 0x22, 0x51]),
+            Err(ImeiError::InvalidDigit(6))
+        );
+    }
+
+    #[test]
+    fn parse_luhn_imei() {
+        assert!(Imei::parse_luhn("490154203237518").is_ok());
+        assert!(Imei::parse("490154203237517").is_ok());
+        assert_eq!(
+            Imei::parse_luhn("490154203237517"),
+            Err(ImeiError::InvalidCheckDigit)
+        );
+    }
+}
@@ -0,0 +1,641 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{AVLDatagram, AVLEventIO, AVLEventIOValue, AVLFrame, AVLRecord, Imei};
+
+/// How the raw value of an IO element should be interpreted
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IoValueType {
+    #[default]
+    Unsigned,
+    Signed,
+    Hex,
+    Ascii,
+}
+
+/// Definition of a single AVL IO element
+///
+/// Mirrors the columns of the AVL ID lists published on the Teltonika wiki
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IoIdDefinition {
+    /// AVL ID as found in the record
+    pub id: u16,
+    pub name: String,
+    /// Size of the value in bytes, `None` when the element is variable length
+    pub bytes: Option<u8>,
+    pub value_type: IoValueType,
+    /// Factor to apply to the raw value to get the real one
+    pub multiplier: f64,
+    pub units: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl IoIdDefinition {
+    /// Creates an unsigned definition with a multiplier of 1 and no units or range.
+    pub fn new(id: u16, name: impl Into<String>) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            bytes: None,
+            value_type: IoValueType::Unsigned,
+            multiplier: 1.0,
+            units: None,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+/// Collection of [`IoIdDefinition`] keyed by AVL ID
+///
+/// Used to map the raw [`AVLEventIO`] values to their real meaning, see [`IoIdMap::describe`]
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct IoIdMap {
+    definitions: BTreeMap<u16, IoIdDefinition>,
+}
+
+impl IoIdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a definition, returning the one previously stored with the same id.
+    pub fn insert(&mut self, definition: IoIdDefinition) -> Option<IoIdDefinition> {
+        self.definitions.insert(definition.id, definition)
+    }
+
+    pub fn get(&self, id: u16) -> Option<&IoIdDefinition> {
+        self.definitions.get(&id)
+    }
+
+    pub fn contains(&self, id: u16) -> bool {
+        self.definitions.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    /// Iterates over the definitions ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = &IoIdDefinition> {
+        self.definitions.values()
+    }
+
+    /// Describes an IO event using its definition.
+    ///
+    /// Returns `None` when the id is not part of the map.
+    pub fn describe(&self, io: &AVLEventIO) -> Option<IoDescription<'_>> {
+        let definition = self.get(io.id)?;
+        let value = match (definition.value_type, &io.value) {
+            (IoValueType::Unsigned | IoValueType::Signed | IoValueType::Hex, value)
+                if value.is_variable() =>
+            {
+                DescribedValue::Text(hex_string(&be_bytes(value)))
+            }
+            (IoValueType::Ascii, value) if value.is_variable() => {
+                DescribedValue::Text(String::from_utf8_lossy(&be_bytes(value)).into_owned())
+            }
+            (IoValueType::Unsigned, value) => {
+                DescribedValue::Number(value.to_f64_scaled(definition.multiplier, 0.0))
+            }
+            (IoValueType::Signed, value) => {
+                DescribedValue::Number(value.to_f64_scaled_signed(definition.multiplier, 0.0))
+            }
+            (IoValueType::Hex, value) => DescribedValue::Text(hex_string(&be_bytes(value))),
+            (IoValueType::Ascii, value) => DescribedValue::Text(
+                String::from_utf8_lossy(&be_bytes(value))
+                    .trim_start_matches('\0')
+                    .to_owned(),
+            ),
+        };
+        Some(IoDescription { definition, value })
+    }
+}
+
+impl FromIterator<IoIdDefinition> for IoIdMap {
+    fn from_iter<T: IntoIterator<Item = IoIdDefinition>>(iter: T) -> Self {
+        Self {
+            definitions: iter.into_iter().map(|d| (d.id, d)).collect(),
+        }
+    }
+}
+
+impl Extend<IoIdDefinition> for IoIdMap {
+    fn extend<T: IntoIterator<Item = IoIdDefinition>>(&mut self, iter: T) {
+        self.definitions.extend(iter.into_iter().map(|d| (d.id, d)));
+    }
+}
+
+/// Value of an IO event after applying its [`IoIdDefinition`]
+#[derive(Debug, PartialEq, Clone)]
+pub enum DescribedValue {
+    /// Numeric value with the multiplier already applied
+    Number(f64),
+    /// Hex or ASCII rendering of the raw value
+    Text(String),
+}
+
+impl fmt::Display for DescribedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+/// IO event described by an [`IoIdMap`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct IoDescription<'a> {
+    pub definition: &'a IoIdDefinition,
+    pub value: DescribedValue,
+}
+
+impl IoDescription<'_> {
+    pub fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    pub fn units(&self) -> Option<&str> {
+        self.definition.units.as_deref()
+    }
+}
+
+impl fmt::Display for IoDescription<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name(), self.value)?;
+        if let Some(units) = self.units() {
+            write!(f, " {units}")?;
+        }
+        Ok(())
+    }
+}
+
+impl AVLEventIOValue {
+    /// Numeric value as `raw * multiplier + offset`, reading the raw value as unsigned.
+    ///
+    /// The computation is carried out in [`f64`] so it never overflows, but [`AVLEventIOValue::U64`]
+    /// values above 2^53 cannot be represented exactly and lose precision.
+    /// Variable length values are not numeric and give [`f64::NAN`].
+    pub fn to_f64_scaled(&self, multiplier: f64, offset: f64) -> f64 {
+        match self {
+            value if value.is_variable() => f64::NAN,
+            value => unsigned(value) as f64 * multiplier + offset,
+        }
+    }
+
+    /// Same as [`AVLEventIOValue::to_f64_scaled`], reading the raw value as a two's complement signed
+    /// number of its width (e.g. `U16(0xFFFF)` is `-1`).
+    pub fn to_f64_scaled_signed(&self, multiplier: f64, offset: f64) -> f64 {
+        match self {
+            value if value.is_variable() => f64::NAN,
+            value => signed(value) as f64 * multiplier + offset,
+        }
+    }
+}
+
+fn unsigned(value: &AVLEventIOValue) -> u64 {
+    match value {
+        AVLEventIOValue::U8(v) => *v as u64,
+        AVLEventIOValue::U16(v) => *v as u64,
+        AVLEventIOValue::U32(v) => *v as u64,
+        AVLEventIOValue::U64(v) => *v,
+        AVLEventIOValue::Variable(_)
+        | AVLEventIOValue::Parsed { .. }
+        | AVLEventIOValue::ParseFailed { .. } => {
+            unreachable!("variable values are not numeric")
+        }
+    }
+}
+
+fn signed(value: &AVLEventIOValue) -> i64 {
+    match value {
+        AVLEventIOValue::U8(v) => *v as i8 as i64,
+        AVLEventIOValue::U16(v) => *v as i16 as i64,
+        AVLEventIOValue::U32(v) => *v as i32 as i64,
+        AVLEventIOValue::U64(v) => *v as i64,
+        AVLEventIOValue::Variable(_)
+        | AVLEventIOValue::Parsed { .. }
+        | AVLEventIOValue::ParseFailed { .. } => {
+            unreachable!("variable values are not numeric")
+        }
+    }
+}
+
+fn be_bytes(value: &AVLEventIOValue) -> Vec<u8> {
+    match value {
+        AVLEventIOValue::U8(v) => v.to_be_bytes().to_vec(),
+        AVLEventIOValue::U16(v) => v.to_be_bytes().to_vec(),
+        AVLEventIOValue::U32(v) => v.to_be_bytes().to_vec(),
+        AVLEventIOValue::U64(v) => v.to_be_bytes().to_vec(),
+        AVLEventIOValue::Variable(bytes)
+        | AVLEventIOValue::Parsed { raw: bytes, .. }
+        | AVLEventIOValue::ParseFailed { raw: bytes, .. } => bytes.clone(),
+    }
+}
+
+/// AVL ID of the GNSS HDOP in the Teltonika IO lists
+pub const HDOP_IO_ID: u16 = 182;
+/// AVL ID of the GNSS PDOP in the Teltonika IO lists
+pub const PDOP_IO_ID: u16 = 181;
+
+/// Quality of the GPS fix of a record, see [`AVLRecord::gps_metrics`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GpsMetrics {
+    pub satellites: u8,
+    /// Horizontal dilution of precision, `None` when not reported
+    pub hdop: Option<f64>,
+    /// Position dilution of precision, `None` when not reported
+    pub pdop: Option<f64>,
+}
+
+impl AVLRecord {
+    /// Extracts the quality of the GPS fix, reading the dilutions of precision from the IO events
+    /// `hdop_id` and `pdop_id` (usually [`HDOP_IO_ID`] and [`PDOP_IO_ID`]).
+    ///
+    /// Values are scaled using their definition in `map`, a dilution is `None`
+    /// when the record does not hold its IO event or `map` has no numeric definition for it.
+    pub fn gps_metrics(&self, map: &IoIdMap, hdop_id: u16, pdop_id: u16) -> GpsMetrics {
+        let dop = |id: u16| {
+            let io = self.io_events.iter().find(|io| io.id == id)?;
+            match map.describe(io)?.value {
+                DescribedValue::Number(value) => Some(value),
+                DescribedValue::Text(_) => None,
+            }
+        };
+        GpsMetrics {
+            satellites: self.satellites,
+            hdop: dop(hdop_id),
+            pdop: dop(pdop_id),
+        }
+    }
+}
+
+impl AVLFrame {
+    /// Ids of the IO events of the records that `map` has no definition for
+    pub fn unknown_io_ids(&self, map: &IoIdMap) -> BTreeSet<u16> {
+        self.records
+            .iter()
+            .flat_map(|record| &record.io_events)
+            .map(|io| io.id)
+            .filter(|id| !map.contains(*id))
+            .collect()
+    }
+}
+
+/// Record normalized for generic telemetry consumers, see [`AVLFrame::to_telemetry`]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TelemetryPoint {
+    /// IMEI of the device, only known for UDP datagrams
+    pub imei: Option<Imei>,
+    pub timestamp: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Speed in km/h
+    pub speed: u16,
+    /// Numeric IO values by id, see [`TelemetryPoint::from_record`]
+    pub io: BTreeMap<u16, i64>,
+}
+
+impl TelemetryPoint {
+    /// Normalizes a record, reading the IO values declared [`IoValueType::Signed`] in `map`
+    /// as two's complement numbers of their width and the others as unsigned.
+    ///
+    /// Variable length values are left out, ids sent more than once keep the value
+    /// chosen by [`AVLRecord::merge_io`]. Unsigned 64 bit values above [`i64::MAX`] wrap around.
+    pub fn from_record(record: &AVLRecord, imei: Option<Imei>, map: &IoIdMap) -> Self {
+        let io = record
+            .merge_io()
+            .into_iter()
+            .filter(|(_, value)| !value.is_variable())
+            .map(|(id, value)| {
+                let signed_type = map
+                    .get(id)
+                    .is_some_and(|definition| definition.value_type == IoValueType::Signed);
+                let number = if signed_type {
+                    signed(&value)
+                } else {
+                    unsigned(&value) as i64
+                };
+                (id, number)
+            })
+            .collect();
+        Self {
+            imei,
+            timestamp: record.timestamp,
+            latitude: record.latitude,
+            longitude: record.longitude,
+            speed: record.speed,
+            io,
+        }
+    }
+}
+
+impl AVLFrame {
+    /// Normalizes the records, without IMEI as TCP frames do not carry it,
+    /// see [`TelemetryPoint::from_record`]
+    pub fn to_telemetry(&self, map: &IoIdMap) -> Vec<TelemetryPoint> {
+        self.records
+            .iter()
+            .map(|record| TelemetryPoint::from_record(record, None, map))
+            .collect()
+    }
+}
+
+impl AVLDatagram {
+    /// Normalizes the records along with the IMEI of the datagram,
+    /// see [`TelemetryPoint::from_record`]
+    pub fn to_telemetry(&self, map: &IoIdMap) -> Vec<TelemetryPoint> {
+        self.records
+            .iter()
+            .map(|record| TelemetryPoint::from_record(record, Some(self.imei.clone()), map))
+            .collect()
+    }
+}
+
+pub(crate) fn hex_string(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{byte:02X}"));
+    }
+    hex
+}
+
+#[cfg(feature = "serde")]
+mod teltonika_json {
+    use serde::de::Error;
+    use serde_json::{Map, Value};
+
+    use super::*;
+
+    /// Finds the value of the first key matching one of `names`,
+    /// ignoring case, spaces and punctuation.
+    fn field<'a>(entry: &'a Map<String, Value>, names: &[&str]) -> Option<&'a Value> {
+        entry.iter().find_map(|(key, value)| {
+            let key: String = key
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .map(|c| c.to_ascii_lowercase())
+                .collect();
+            names.contains(&key.as_str()).then_some(value)
+        })
+    }
+
+    /// Reads a number that may have been written as a string, `"-"` and `""` mean missing.
+    fn number(value: Option<&Value>) -> Option<f64> {
+        match value? {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => string.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn text(value: Option<&Value>) -> Option<String> {
+        match value? {
+            Value::String(string) => {
+                let string = string.trim();
+                (!string.is_empty() && string != "-").then(|| string.to_owned())
+            }
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        }
+    }
+
+    fn value_type(value: Option<&Value>) -> IoValueType {
+        let value_type = text(value).unwrap_or_default().to_ascii_lowercase();
+        if value_type.contains("unsigned") {
+            IoValueType::Unsigned
+        } else if value_type.contains("signed") {
+            IoValueType::Signed
+        } else if value_type.contains("hex") {
+            IoValueType::Hex
+        } else if value_type.contains("ascii") || value_type.contains("string") {
+            IoValueType::Ascii
+        } else {
+            IoValueType::Unsigned
+        }
+    }
+
+    pub(super) fn definition(entry: &Map<String, Value>) -> serde_json::Result<IoIdDefinition> {
+        let id = number(field(
+            entry,
+            &["id", "avlid", "propertyid", "propertyidinavlpacket"],
+        ))
+        .filter(|id| id.fract() == 0.0 && (0.0..=u16::MAX as f64).contains(id))
+        .ok_or_else(|| serde_json::Error::custom("missing or invalid AVL ID"))?
+            as u16;
+        let name = text(field(entry, &["name", "propertyname"]))
+            .ok_or_else(|| serde_json::Error::custom(format!("missing name for AVL ID {id}")))?;
+
+        Ok(IoIdDefinition {
+            id,
+            name,
+            bytes: number(field(entry, &["bytes", "size"]))
+                .filter(|bytes| (1.0..=u8::MAX as f64).contains(bytes))
+                .map(|bytes| bytes as u8),
+            value_type: value_type(field(entry, &["type"])),
+            multiplier: number(field(entry, &["multiplier"])).unwrap_or(1.0),
+            units: text(field(entry, &["units", "unit"])),
+            min: number(field(entry, &["min", "minvalue", "valuerangemin"])),
+            max: number(field(entry, &["max", "maxvalue", "valuerangemax"])),
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl IoIdMap {
+    /// Loads an AVL ID list in the JSON format published on the Teltonika wiki.
+    ///
+    /// The input is an array of objects, one for each AVL ID, holding the
+    /// `id`, `name`, `bytes`, `type`, `multiplier`, `units`, `min` and `max` fields.
+    /// Field names are matched ignoring case, spaces and punctuation so the wiki
+    /// column headers (e.g. `"Property ID in AVL packet"`, `"Value range Min"`) are accepted too.
+    /// Numeric fields may be written as strings, `"-"` marks a missing value.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input is not valid JSON or an entry misses its id or name.
+    pub fn from_teltonika_json<R: std::io::Read>(reader: R) -> serde_json::Result<Self> {
+        let entries: Vec<serde_json::Map<String, serde_json::Value>> =
+            serde_json::from_reader(reader)?;
+        entries.iter().map(teltonika_json::definition).collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl crate::AVLRecord {
+    /// IO events as a JSON object keyed by the names in `map`, with their scaled values.
+    ///
+    /// Names are used as they are in the map, ids not found in it are keyed as `io_<id>` with their raw value.
+    /// Numbers without a fractional part are rendered as integers, texts as strings.
+    pub fn io_json(&self, map: &IoIdMap) -> serde_json::Map<String, serde_json::Value> {
+        use serde_json::Value;
+
+        self.io_events
+            .iter()
+            .map(|io| match map.describe(io) {
+                Some(description) => {
+                    let value = match description.value {
+                        DescribedValue::Number(number)
+                            if number.fract() == 0.0 && number.abs() < i64::MAX as f64 =>
+                        {
+                            Value::from(number as i64)
+                        }
+                        DescribedValue::Number(number) => Value::from(number),
+                        DescribedValue::Text(text) => Value::from(text),
+                    };
+                    (description.definition.name.clone(), value)
+                }
+                None => {
+                    let value = match &io.value {
+                        AVLEventIOValue::Variable(bytes)
+                        | AVLEventIOValue::ParseFailed { raw: bytes, .. } => {
+                            Value::from(hex_string(bytes))
+                        }
+                        AVLEventIOValue::Parsed { value, .. } => {
+                            serde_json::to_value(value).unwrap_or_default()
+                        }
+                        value => Value::from(unsigned(value)),
+                    };
+                    (format!("io_{}", io.id), value)
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+
+    #[test]
+    fn scale_value() {
+        assert_eq!(
+            AVLEventIOValue::U16(12896).to_f64_scaled(0.001, 0.0),
+            12.896
+        );
+        assert_eq!(AVLEventIOValue::U8(40).to_f64_scaled(1.0, -40.0), 0.0);
+        assert!(AVLEventIOValue::Variable(vec![1])
+            .to_f64_scaled(1.0, 0.0)
+            .is_nan());
+    }
+
+    #[test]
+    fn scale_large_value() {
+        let value = AVLEventIOValue::U64(u64::MAX);
+        assert_eq!(value.to_f64_scaled(1.0, 0.0), u64::MAX as f64);
+        assert_eq!(value.to_f64_scaled(0.001, 0.0), 18446744073709551.615);
+        // 2^53 + 1 is not representable and rounds to 2^53
+        let value = AVLEventIOValue::U64((1 << 53) + 1);
+        assert_eq!(value.to_f64_scaled(1.0, 0.0), (1u64 << 53) as f64);
+    }
+
+    #[test]
+    fn scale_signed_value() {
+        // -550 * 0.1
+        let value = AVLEventIOValue::U32(-550i32 as u32);
+        assert_eq!(value.to_f64_scaled_signed(0.1, 0.0), -55.0);
+        assert_eq!(value.to_f64_scaled(0.1, 0.0), 429496674.6);
+        assert_eq!(
+            AVLEventIOValue::U16(0xFFFF).to_f64_scaled_signed(1.0, 10.0),
+            9.0
+        );
+    }
+
+    #[test]
+    fn record_gps_metrics() {
+        let input = hex::decode(GPS_METRICS_FRAME).unwrap();
+        let (_, frame) = crate::parser::tcp_frame(&input).unwrap();
+        let record = &frame.unwrap_avl().records[0];
+
+        let mut map = IoIdMap::new();
+        for (id, name) in [(HDOP_IO_ID, "GNSS HDOP"), (PDOP_IO_ID, "GNSS PDOP")] {
+            let mut definition = IoIdDefinition::new(id, name);
+            definition.multiplier = 0.1;
+            map.insert(definition);
+        }
+        assert_eq!(
+            record.gps_metrics(&map, HDOP_IO_ID, PDOP_IO_ID),
+            GpsMetrics {
+                satellites: 9,
+                hdop: Some(0.9),
+                pdop: Some(1.5),
+            }
+        );
+
+        // Unknown to the map, or missing from the record
+        let metrics = record.gps_metrics(&IoIdMap::new(), HDOP_IO_ID, 1000);
+        assert_eq!(metrics.hdop, None);
+        assert_eq!(metrics.pdop, None);
+    }
+
+    #[test]
+    fn frame_unknown_io_ids() {
+        let input = hex::decode(GPS_METRICS_FRAME).unwrap();
+        let (_, frame) = crate::parser::tcp_frame(&input).unwrap();
+        let frame = frame.unwrap_avl();
+
+        let map: IoIdMap = [
+            IoIdDefinition::new(1, "Digital Input 1"),
+            IoIdDefinition::new(66, "External Voltage"),
+            IoIdDefinition::new(239, "Ignition"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            frame.unknown_io_ids(&map),
+            BTreeSet::from([21, HDOP_IO_ID, PDOP_IO_ID])
+        );
+        assert_eq!(
+            frame.unknown_io_ids(&IoIdMap::new()).len(),
+            frame.records[0].io_events.len()
+        );
+    }
+
+    #[test]
+    fn datagram_to_telemetry() {
+        let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+        let (_, datagram) = crate::parser::udp_datagram(&input).unwrap();
+        let record = datagram.records[0].clone();
+
+        let mut signed_definition = IoIdDefinition::new(66, "External Voltage");
+        signed_definition.value_type = IoValueType::Signed;
+        let map: IoIdMap = [signed_definition].into_iter().collect();
+
+        let points = datagram.to_telemetry(&map);
+        assert_eq!(points.len(), 1);
+        let point = &points[0];
+        assert_eq!(point.imei.as_ref().unwrap().as_str(), "352093086403655");
+        assert_eq!(point.timestamp, record.timestamp);
+        assert_eq!(point.speed, 0);
+        assert_eq!(
+            point.io,
+            BTreeMap::from([(1, 1), (21, 3), (66, 0x5DBC_u16 as i16 as i64)])
+        );
+
+        let points = crate::AVLFrame {
+            codec: crate::Codec::C8,
+            records: vec![record],
+            crc16: 0,
+        }
+        .to_telemetry(&IoIdMap::new());
+        assert_eq!(points[0].imei, None);
+        assert_eq!(points[0].io[&66], 0x5DBC);
+    }
+}
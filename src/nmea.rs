@@ -0,0 +1,57 @@
+use crate::AVLRecord;
+
+/// Knots in a km/h
+const KMH_TO_KNOTS: f64 = 1.0 / 1.852;
+
+impl AVLRecord {
+    /// Renders the record as a NMEA `$GPRMC` sentence, checksum included.
+    ///
+    /// Records without a GPS fix (no satellites in use) are rendered as a void (`V`) sentence
+    /// keeping only the time and date.
+    pub fn to_gprmc(&self) -> String {
+        let time = self.timestamp.format("%H%M%S");
+        let centis = self.timestamp.timestamp_subsec_millis() / 10;
+        let date = self.timestamp.format("%d%m%y");
+
+        let fields = if self.satellites == 0 {
+            format!("GPRMC,{time}.{centis:02},V,,,,,,,{date},,,N")
+        } else {
+            let (latitude, north_south) = nmea_coordinate(self.latitude_raw, 2, 'N', 'S');
+            let (longitude, east_west) = nmea_coordinate(self.longitude_raw, 3, 'E', 'W');
+            let speed = self.speed as f64 * KMH_TO_KNOTS;
+            format!(
+                "GPRMC,{time}.{centis:02},A,{latitude},{north_south},{longitude},{east_west},{speed:.1},{:.1},{date},,,A",
+                self.angle as f64
+            )
+        };
+
+        format!("${fields}*{:02X}", nmea_checksum(&fields))
+    }
+}
+
+/// Formats a coordinate in degrees * 10^7 as `(d)ddmm.mmmm`, followed by its hemisphere.
+fn nmea_coordinate(
+    raw: i32,
+    degree_digits: usize,
+    positive: char,
+    negative: char,
+) -> (String, char) {
+    // 1/10000 of minute = 10^7 / (60 * 10^4) raw units, rounded
+    let units = (raw.unsigned_abs() as u64 * 6 + 50) / 100;
+    let degrees = units / 600_000;
+    let minutes = units % 600_000;
+    let hemisphere = if raw < 0 { negative } else { positive };
+    (
+        format!(
+            "{degrees:0degree_digits$}{:02}.{:04}",
+            minutes / 10_000,
+            minutes % 10_000
+        ),
+        hemisphere,
+    )
+}
+
+/// XOR of all the bytes between `$` and `*`
+fn nmea_checksum(fields: &str) -> u8 {
+    fields.bytes().fold(0, |checksum, byte| checksum ^ byte)
+}
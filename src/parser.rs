@@ -1,37 +1,81 @@
-use chrono::{TimeZone, Utc};
+use std::{borrow::Borrow, collections::BTreeMap, ops::Range};
+
+use chrono::{FixedOffset, TimeZone, Utc};
 use nom::{
-    bytes::streaming::tag,
-    character::streaming::anychar,
-    combinator::{cond, verify},
+    bytes::streaming::{tag, take},
+    combinator::{all_consuming, complete, cond, map, map_res, verify},
     error::ParseError,
     multi::{count, length_count, length_data},
     number::streaming::{be_i32, be_u16, be_u32, be_u64, be_u8},
     IResult, Parser,
 };
 
-use crate::protocol::*;
+use crate::{
+    protocol::*, CrcAlgorithm, CrcRange, Imei, ImeiFormat, TeltonikaParseError, VariableParsers,
+};
 
 /// Parse an imei
 ///
-/// Following the teltonika protocol, takes a `&[u8]`: [`u16`] as `length` and `length` bytes as [`String`]
-pub fn imei(input: &[u8]) -> IResult<&[u8], String> {
-    let (input, imei) = length_count(be_u16, anychar)(input)?;
-    Ok((input, imei.iter().collect()))
+/// Following the teltonika protocol, takes a `&[u8]`: [`u16`] as `length` and `length` bytes as [`Imei`]
+///
+/// Fails if the bytes are not a valid [`Imei`]
+pub fn imei(input: &[u8]) -> IResult<&[u8], Imei> {
+    map_res(length_data(be_u16), |imei: &[u8]| {
+        std::str::from_utf8(imei)
+            .ok()
+            .and_then(|imei| Imei::parse(imei).ok())
+            .ok_or(())
+    })(input)
+}
+
+/// Parse an imei sent in the given [`ImeiFormat`], see [`imei`]
+///
+/// ```rust
+/// use nom_teltonika::{parser::imei_with_format, ImeiFormat};
+///
+/// let input = [0x03, 0x56, 0x30, 0x70, 0x42, 0x44, 0x10, 0x13];
+/// let (_, imei) = imei_with_format(ImeiFormat::Bcd)(&input).unwrap();
+/// assert_eq!(imei.as_str(), "356307042441013");
+/// ```
+pub fn imei_with_format(format: ImeiFormat) -> impl Fn(&[u8]) -> IResult<&[u8], Imei> {
+    move |input| match format {
+        ImeiFormat::LengthPrefixedAscii => imei(input),
+        ImeiFormat::Bcd => bcd_imei(input),
+    }
+}
+
+/// Parse the 8 bytes of packed digits of an imei, see [`Imei::from_bcd`]
+fn bcd_imei(input: &[u8]) -> IResult<&[u8], Imei> {
+    map_res(take(8usize), |bcd: &[u8]| {
+        Imei::from_bcd(bcd.try_into().expect("8 bytes taken"))
+    })(input)
 }
 
 fn codec(input: &[u8]) -> IResult<&[u8], Codec> {
-    let (input, codec) = be_u8(input)?;
-    Ok((input, codec.into()))
+    map_res(be_u8, Codec::try_from)(input)
+}
+
+/// Parse a record count as wide as the codec wants it, see [`Codec::record_count_width`]
+fn record_count(codec: Codec) -> impl Fn(&[u8]) -> IResult<&[u8], usize> {
+    move |input| {
+        map(take(codec.record_count_width()), |count: &[u8]| {
+            count
+                .iter()
+                .fold(0, |count, byte| count << 8 | *byte as usize)
+        })(input)
+    }
+}
+
+fn codec12_type(input: &[u8]) -> IResult<&[u8], Codec12Type> {
+    map_res(be_u8, Codec12Type::try_from)(input)
 }
 
 fn priority(input: &[u8]) -> IResult<&[u8], Priority> {
-    let (input, priority) = be_u8(input)?;
-    Ok((input, priority.into()))
+    map_res(be_u8, Priority::try_from)(input)
 }
 
 fn event_generation_cause(input: &[u8]) -> IResult<&[u8], EventGenerationCause> {
-    let (input, generation_type) = be_u8(input)?;
-    Ok((input, generation_type.into()))
+    map_res(be_u8, EventGenerationCause::try_from)(input)
 }
 
 fn event_id<'a>(codec: Codec) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], u16> {
@@ -74,66 +118,246 @@ where
     }
 }
 
+/// Parse `count` events with `parser`, pushing them into `io_events`
+fn push_events<'a, F>(
+    mut parser: F,
+    count: usize,
+    io_events: &mut Vec<AVLEventIO>,
+    mut input: &'a [u8],
+) -> IResult<&'a [u8], ()>
+where
+    F: Parser<&'a [u8], AVLEventIO, nom::error::Error<&'a [u8]>>,
+{
+    for _ in 0..count {
+        let (rest, event) = parser.parse(input)?;
+        io_events.push(event);
+        input = rest;
+    }
+    Ok((input, ()))
+}
+
+/// Parse the IO events of a record, failing with [`nom::error::ErrorKind::TooLarge`]
+/// as soon as the counts of the groups read so far add up to more than `max_io_events`
+///
+/// The events of all the groups are parsed into a single vector of `capacity`,
+/// the count declared by the record.
 fn io_events<'a>(
     codec: Codec,
+    max_io_events: Option<usize>,
+    capacity: usize,
 ) -> impl Parser<&'a [u8], Vec<AVLEventIO>, nom::error::Error<&'a [u8]>> {
-    move |input| {
-        let (input, u8_ios) = length_count(
-            event_count(codec),
+    move |input: &'a [u8]| {
+        let mut total = 0;
+        // Reads the count of a group, checking it before parsing its events
+        let mut group_count = |input: &'a [u8]| {
+            let (rest, group_count) = event_count(codec)(input)?;
+            total += group_count as usize;
+            check_limit(input, total, max_io_events)?;
+            Ok((rest, group_count as usize))
+        };
+
+        // Each event takes at least 2 bytes, do not trust counts the input cannot hold
+        let mut io_events = Vec::with_capacity(capacity.min(input.len() / 2));
+
+        let (input, u8_count) = group_count(input)?;
+        let (input, ()) = push_events(
             event(codec, be_u8).map(|(id, val)| AVLEventIO {
                 id,
                 value: AVLEventIOValue::U8(val),
             }),
-        )(input)?;
+            u8_count,
+            &mut io_events,
+            input,
+        )?;
 
-        let (input, u16_ios) = length_count(
-            event_count(codec),
+        let (input, u16_count) = group_count(input)?;
+        let (input, ()) = push_events(
             event(codec, be_u16).map(|(id, val)| AVLEventIO {
                 id,
                 value: AVLEventIOValue::U16(val),
             }),
-        )(input)?;
+            u16_count,
+            &mut io_events,
+            input,
+        )?;
 
-        let (input, u32_ios) = length_count(
-            event_count(codec),
+        let (input, u32_count) = group_count(input)?;
+        let (input, ()) = push_events(
             event(codec, be_u32).map(|(id, val)| AVLEventIO {
                 id,
                 value: AVLEventIOValue::U32(val),
             }),
-        )(input)?;
+            u32_count,
+            &mut io_events,
+            input,
+        )?;
 
-        let (input, u64_ios) = length_count(
-            event_count(codec),
+        let (input, u64_count) = group_count(input)?;
+        let (input, ()) = push_events(
             event(codec, be_u64).map(|(id, val)| AVLEventIO {
                 id,
                 value: AVLEventIOValue::U64(val),
             }),
-        )(input)?;
+            u64_count,
+            &mut io_events,
+            input,
+        )?;
 
-        let (input, xb_ios) = cond(
-            codec == Codec::C8Ext,
-            length_count(
-                event_count(codec),
-                event(codec, length_count(event_count(codec), be_u8)).map(|(id, val)| AVLEventIO {
-                    id,
-                    value: AVLEventIOValue::Variable(val),
+        let input = if codec == Codec::C8Ext {
+            let (input, xb_count) = group_count(input)?;
+            let (input, ()) = push_events(
+                event(codec, length_data(event_count(codec))).map(|(id, val): (u16, &[u8])| {
+                    AVLEventIO {
+                        id,
+                        value: AVLEventIOValue::Variable(val.to_vec()),
+                    }
                 }),
-            ),
-        )(input)?;
+                xb_count,
+                &mut io_events,
+                input,
+            )?;
+            input
+        } else {
+            input
+        };
+        Ok((input, io_events))
+    }
+}
+
+/// Parse `count` IO events of the fixed `width` group (1, 2, 4 or 8 bytes) of an AVL record
+///
+/// The input starts after the count of the group, the remaining input starts at the count of the next one.
+/// Fails with [`nom::error::ErrorKind::Switch`] on other widths and on codecs other than AVL ones.
+///
+/// ```rust
+/// use nom_teltonika::{parser::parse_io_group, AVLEventIO, AVLEventIOValue, Codec};
+///
+/// let input = [0x00, 0x42, 0x5E, 0x10, 0x00];
+/// let (rest, events) = parse_io_group(Codec::C8Ext, 2, 1, &input).unwrap();
+/// assert_eq!(rest, [0x00]);
+/// assert_eq!(events, [AVLEventIO { id: 0x42, value: AVLEventIOValue::U16(0x5E10) }]);
+/// ```
+pub fn parse_io_group(
+    codec: Codec,
+    width: usize,
+    count: usize,
+    input: &[u8],
+) -> IResult<&[u8], Vec<AVLEventIO>> {
+    if !matches!(codec, Codec::C8 | Codec::C8Ext | Codec::C16) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Switch,
+        )));
+    }
+    let value = move |input| match width {
+        1 => be_u8.map(AVLEventIOValue::U8).parse(input),
+        2 => be_u16.map(AVLEventIOValue::U16).parse(input),
+        4 => be_u32.map(AVLEventIOValue::U32).parse(input),
+        8 => be_u64.map(AVLEventIOValue::U64).parse(input),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Switch,
+        ))),
+    };
+    nom::multi::count(
+        event(codec, value).map(|(id, value)| AVLEventIO { id, value }),
+        count,
+    )(input)
+}
+
+/// Fails with [`nom::error::ErrorKind::TooLarge`] on `input` when `count` exceeds `max`
+fn check_limit(
+    input: &[u8],
+    count: usize,
+    max: Option<usize>,
+) -> Result<(), nom::Err<nom::error::Error<&[u8]>>> {
+    match max {
+        Some(max) if count > max => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TooLarge,
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Parse an AVL record as set by `options`
+fn record<'a, 'o>(
+    codec: Codec,
+    options: &'o ParseOptions,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], AVLRecord> + 'o {
+    move |input| {
+        let timestamp_input = input;
+        let (input, timestamp) = be_u64(input)?;
+        let (input, priority) = priority(input)?;
+
+        let (input, longitude_raw) = be_i32(input)?;
+        let (input, latitude_raw) = be_i32(input)?;
+        let (input, altitude) = be_u16(input)?;
+        let (input, angle) = be_u16(input)?;
+        let (input, satellites) = be_u8(input)?;
+        let (input, speed) = be_u16(input)?;
+        let (angle, speed) = if options.quirk_swap_speed_angle {
+            (speed, angle)
+        } else {
+            (angle, speed)
+        };
+
+        let (input, trigger_event_id) = event_id(codec)(input)?;
+        let (input, generation_type) = cond(codec == Codec::C16, event_generation_cause)(input)?;
 
-        let mut io_events = vec![];
-        io_events.extend(u8_ios);
-        io_events.extend(u16_ios);
-        io_events.extend(u32_ios);
-        io_events.extend(u64_ios);
-        if let Some(xb_ios) = xb_ios {
-            io_events.extend(xb_ios);
+        let (rest, ios_count) = event_count(codec)(input)?;
+        check_limit(input, ios_count as usize, options.max_io_per_record)?;
+        let (input, mut io_events) = verify(
+            io_events(codec, options.max_io_per_record, ios_count as usize),
+            |events: &Vec<AVLEventIO>| events.len() as u16 == ios_count,
+        )(rest)?;
+
+        // contruct a datetime using the timestamp in since the unix epoch,
+        // failing on the ones chrono cannot represent
+        let timestamp = Utc
+            .timestamp_millis_opt(timestamp as i64)
+            .single()
+            .and_then(|timestamp| match options.timestamp_is_local {
+                Some(offset) => timestamp
+                    .checked_sub_signed(chrono::Duration::seconds(offset.local_minus_utc().into())),
+                None => Some(timestamp),
+            })
+            .ok_or(nom::Err::Failure(nom::error::Error::new(
+                timestamp_input,
+                nom::error::ErrorKind::MapOpt,
+            )))?;
+
+        if options.drop_padding_io {
+            io_events.retain(|io| io.id != 0);
         }
-        Ok((input, io_events))
+
+        let longitude = longitude_raw as f64 / 10000000.0;
+        let latitude = latitude_raw as f64 / 10000000.0;
+
+        let mut record = AVLRecord {
+            timestamp,
+            priority,
+            longitude,
+            latitude,
+            longitude_raw,
+            latitude_raw,
+            altitude,
+            angle,
+            satellites,
+            speed,
+            trigger_event_id,
+            generation_type,
+            io_events,
+        };
+        record.parse_variables(&options.variable_parsers);
+        Ok((input, record))
     }
 }
 
-fn record<'a>(codec: Codec) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], AVLRecord> {
+/// Parse a single record keeping its IO events grouped by width, see [`AVLRecordRaw`]
+///
+/// Unlike the records parsed within frames, the declared IO count is not checked against the events
+pub fn record_raw<'a>(codec: Codec) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], AVLRecordRaw> {
     move |input| {
         let (input, timestamp) = be_u64(input)?;
         let (input, priority) = priority(input)?;
@@ -148,20 +372,22 @@ fn record<'a>(codec: Codec) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], AVLReco
         let (input, trigger_event_id) = event_id(codec)(input)?;
         let (input, generation_type) = cond(codec == Codec::C16, event_generation_cause)(input)?;
 
-        let (input, ios_count) = event_count(codec)(input)?;
-        let (input, io_events) = verify(io_events(codec), |events: &Vec<AVLEventIO>| {
-            events.len() as u16 == ios_count
-        })(input)?;
-
-        // contruct a datetime using the timestamp in since the unix epoch
-        let timestamp = Utc.timestamp_millis_opt(timestamp as i64).single().unwrap();
-
-        let longitude = longitude as f64 / 10000000.0;
-        let latitude = latitude as f64 / 10000000.0;
+        let (input, io_count) = event_count(codec)(input)?;
+        let (input, io_u8) = length_count(event_count(codec), event(codec, be_u8))(input)?;
+        let (input, io_u16) = length_count(event_count(codec), event(codec, be_u16))(input)?;
+        let (input, io_u32) = length_count(event_count(codec), event(codec, be_u32))(input)?;
+        let (input, io_u64) = length_count(event_count(codec), event(codec, be_u64))(input)?;
+        let (input, io_variable) = cond(
+            codec == Codec::C8Ext,
+            length_count(
+                event_count(codec),
+                event(codec, length_count(event_count(codec), be_u8)),
+            ),
+        )(input)?;
 
         Ok((
             input,
-            AVLRecord {
+            AVLRecordRaw {
                 timestamp,
                 priority,
                 longitude,
@@ -172,42 +398,223 @@ fn record<'a>(codec: Codec) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], AVLReco
                 speed,
                 trigger_event_id,
                 generation_type,
-                io_events,
+                io_count,
+                io_u8,
+                io_u16,
+                io_u32,
+                io_u64,
+                io_variable,
             },
         ))
     }
 }
 
+/// Parse the records of an AVL data field, handing each one to `f` as soon as it is parsed
+///
+/// Takes the data field after the codec: the number of records, the records and the number of records again.
+/// Records are not collected, so memory stays bounded whatever the size of the frame.
+///
+/// Returns the number of records parsed, the CRC is not checked
+pub fn parse_records(
+    input: &[u8],
+    codec: Codec,
+    mut f: impl FnMut(AVLRecord),
+) -> IResult<&[u8], usize> {
+    let options = ParseOptions::default();
+    let mut parse_record = record(codec, &options);
+    let (mut input, records_count) = record_count(codec)(input)?;
+    for _ in 0..records_count {
+        let (rest, record) = parse_record(input)?;
+        f(record);
+        input = rest;
+    }
+    let (input, _records_count) = verify(record_count(codec), |number_of_records| {
+        *number_of_records == records_count
+    })(input)?;
+    Ok((input, records_count))
+}
+
 /// Parse a single command response.
-/// 
+///
 /// That means a 4 bytes length and X bytes characters.
-fn command_response(input: &[u8]) -> IResult<&[u8], String> {
-    let (input, response) = length_count(be_u32, anychar)(input)?;
-    Ok((input, response.iter().collect()))
+fn command_response(input: &[u8]) -> IResult<&[u8], CommandResponse> {
+    let (input, response) = length_data(be_u32)(input)?;
+    Ok((input, response.into()))
+}
+
+/// Split the `KEY:VALUE` pairs of a `getinfo` command response, e.g. `GPS:1 SAT:0 NOGPS:0:30`
+///
+/// Values are split from their key at the first colon, so they can hold colons themselves.
+/// Tokens not starting with a key, such as the time of `INI:2019/7/22 7:22`,
+/// belong to the value before them and are joined to it with a space.
+pub fn parse_getinfo(response: &str) -> BTreeMap<String, String> {
+    let mut pairs = BTreeMap::new();
+    let mut last_key = None;
+    for token in response.split_whitespace() {
+        match token.split_once(':') {
+            Some((key, value))
+                if key.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && key.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                pairs.insert(key.to_string(), value.to_string());
+                last_key = Some(key);
+            }
+            _ => {
+                if let Some(value) = last_key.and_then(|key| pairs.get_mut(key)) {
+                    value.push(' ');
+                    value.push_str(token);
+                }
+            }
+        }
+    }
+    pairs
 }
 
 /// Parse a TCP teltonika frame
 ///
 /// Either parse a GPRS Command response or an AVL Record response
 ///
+/// GPRS frames holding commands (type `0x05`) are parsed too, unknown types fail with [`nom::error::ErrorKind::MapRes`],
+/// see [`TeltonikaParseError::UnknownCodec12Type`] reported by [`tcp_frame_complete`]
+///
+/// Codec 14 responses are parsed as GPRS frames too, holding the IMEI of the device.
+/// Responses carrying different IMEIs fail with [`nom::error::ErrorKind::Verify`].
+/// Codec 13 messages, sent by devices with a timestamp, are not parsed:
+/// they fail with [`nom::error::ErrorKind::Switch`] as unknown codecs do,
+/// see [`TeltonikaParseError::UnsupportedCodec`] reported by [`tcp_frame_complete`]
+///
+/// A zero preamble directly followed by the preamble of the next frame or keep-alive is a keep-alive,
+/// parsed as [`TeltonikaFrame::KeepAlive`] consuming only its 4 bytes.
+/// Received alone it may be the start of a frame and is incomplete, while [`tcp_frame_complete`] parses it as a keep-alive.
+/// A frame declaring a data length of 0 fails with [`nom::error::ErrorKind::LengthValue`]
+///
 /// It does 3 main error checks:
 /// - Preamble is all zeroes
 /// - Both counts coincide
 /// - Computes CRC and verifies it against the one sent
+///
+/// A data length exceeding the input is reported as [`nom::Err::Incomplete`], as more bytes may follow,
+/// while a data field too short for its content fails with [`nom::error::ErrorKind::Complete`]
+/// and one longer than its content with [`nom::error::ErrorKind::Eof`]
 pub fn tcp_frame(input: &[u8]) -> IResult<&[u8], TeltonikaFrame> {
+    tcp_frame_with_options(ParseOptions::default())(input)
+}
+
+/// Options changing how frames are parsed, the defaults follow the specification
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    /// Algorithm of the frame CRC
+    pub crc: CrcAlgorithm,
+    /// Parsers of the variable length IO values, see [`AVLRecord::parse_variables`]
+    pub variable_parsers: VariableParsers,
+    /// Maximum number of records of a frame, more fail with [`nom::error::ErrorKind::TooLarge`]
+    pub max_records: Option<usize>,
+    /// Maximum number of IO events of each record, more fail with [`nom::error::ErrorKind::TooLarge`]
+    ///
+    /// Both the total count of the record and the counts of its groups added up are checked,
+    /// before allocating the events.
+    pub max_io_per_record: Option<usize>,
+    /// Drops the IO events with id 0, sent as padding by some devices,
+    /// see [`AVLRecord::io_events_without_padding`]
+    ///
+    /// The IO count of the record is still checked against all the IO events received.
+    pub drop_padding_io: bool,
+    /// Reads the speed before the satellites and the angle after them,
+    /// for firmwares swapping the two fields in AVL records
+    pub quirk_swap_speed_angle: bool,
+    /// Offset of the local time sent by devices configured against the specification,
+    /// the timestamps are converted from it to UTC
+    ///
+    /// `None` reads the timestamps as UTC.
+    pub timestamp_is_local: Option<FixedOffset>,
+    /// Reads the data length of TCP frames as 2 bytes instead of 4,
+    /// for legacy aggregators re-framing the data with a shorter length
+    pub u16_data_length: bool,
+}
+
+impl ParseOptions {
+    /// Length of the preamble and the data length of TCP frames
+    fn header_len(&self) -> usize {
+        if self.u16_data_length {
+            4 + 2
+        } else {
+            4 + 4
+        }
+    }
+}
+
+/// Parse a TCP teltonika frame with custom [`ParseOptions`], see [`tcp_frame`]
+///
+/// The options can be owned or shared, e.g. borrowed or behind an [`Arc`](std::sync::Arc), to avoid cloning them.
+pub fn tcp_frame_with_options(
+    options: impl Borrow<ParseOptions>,
+) -> impl Fn(&[u8]) -> IResult<&[u8], TeltonikaFrame> {
+    move |input| tcp_frame_inner(input, options.borrow(), true)
+}
+
+/// Parse a TCP frame whose CRC is already known to be valid, e.g. checked with [`check_crc`] at ingest
+///
+/// Same as [`tcp_frame`] without computing the CRC, which is only read.
+/// A frame with a wrong CRC is parsed as any other, only use it on frames checked before.
+pub fn tcp_frame_trusted(input: &[u8]) -> IResult<&[u8], TeltonikaFrame> {
+    tcp_frame_inner(input, &ParseOptions::default(), false)
+}
+
+/// Whether the zero preamble before `input` is a keep-alive, directly followed by the preamble
+/// of the next frame or keep-alive
+///
+/// Read as a data length, 4 zero bytes would declare an empty data field, so they are always a preamble.
+/// A preamble received alone is incomplete, the rest of the frame may follow.
+fn is_keep_alive(input: &[u8]) -> Result<bool, nom::Err<nom::error::Error<&[u8]>>> {
+    let preamble_len = input.len().min(4);
+    if input[..preamble_len].iter().any(|&byte| byte != 0) {
+        return Ok(false);
+    }
+    if input.len() < 4 {
+        // Either the data length of this frame or another preamble, wait for more bytes
+        return Err(nom::Err::Incomplete(nom::Needed::new(4 - input.len())));
+    }
+    Ok(true)
+}
+
+/// Parse a TCP frame, the CRC is only read when `check_crc` is false
+fn tcp_frame_inner<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+    check_crc: bool,
+) -> IResult<&'a [u8], TeltonikaFrame> {
     let (input, _preamble) = tag("\0\0\0\0")(input)?;
-    let (input, data) = length_data(be_u32)(input)?;
+    if is_keep_alive(input)? {
+        return Ok((input, TeltonikaFrame::KeepAlive));
+    }
+    let length_input = input;
+    let (input, data) = if options.u16_data_length {
+        length_data(be_u16)(input)?
+    } else {
+        length_data(be_u32)(input)?
+    };
+    if data.is_empty() {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            length_input,
+            nom::error::ErrorKind::LengthValue,
+        )));
+    }
 
-    let calculated_crc16 = crate::crc16(data);
-    let (data, codec) = codec(data)?;
+    // The whole data field is available, running out of it or leaving bytes in it means the frame is malformed
+    let calculated_crc16 = if check_crc {
+        options.crc.checksum(data)
+    } else {
+        0
+    };
+    // Unknown codecs and the ones not parsed are reported alike
+    let unsupported_codec =
+        || nom::Err::Error(nom::error::Error::new(data, nom::error::ErrorKind::Switch));
+    let (data, codec) = complete(codec)(data).map_err(|_| unsupported_codec())?;
 
     Ok(match codec {
         Codec::C8 | Codec::C8Ext | Codec::C16 => {
-            let (data, records) = length_count(be_u8, record(codec))(data)?;
-            let (_data, _records_count) = verify(be_u8, |number_of_records| {
-                *number_of_records as usize == records.len()
-            })(data)?;
-            let (input, crc16) = verify(be_u32, |crc16| *crc16 == calculated_crc16 as u32)(input)?;
+            let (_data, records) = complete(all_consuming(avl_data(codec, options)))(data)?;
+            let (input, crc16) = frame_crc(calculated_crc16, check_crc)(input)?;
             (
                 input,
                 TeltonikaFrame::AVL(AVLFrame {
@@ -217,176 +624,713 @@ pub fn tcp_frame(input: &[u8]) -> IResult<&[u8], TeltonikaFrame> {
                 }),
             )
         }
-        Codec::C12 => {
-            let (data, response_qty) = be_u8(data)?;
-
-            // Type of a command response
-            let (data, _) = tag("\x06")(data)?;
-            let (data, responses) = count(command_response, response_qty as usize)(data)?;
-            let (_data, _response_qty) = verify(be_u8, |number_of_responses| {
-                *number_of_responses as usize == responses.len()
-            })(data)?;
-
-            let (input, crc16) = verify(be_u32, |crc16| *crc16 == calculated_crc16 as u32)(input)?;
+        Codec::C12 | Codec::C14 => {
+            let (_data, (command_type, responses, imei)) =
+                complete(all_consuming(gprs_data(codec)))(data)?;
+            let (input, crc16) = frame_crc(calculated_crc16, check_crc)(input)?;
             (
                 input,
                 TeltonikaFrame::GPRS(GPRSFrame {
                     codec,
+                    command_type,
                     command_responses: responses,
+                    imei,
                     crc16,
                 }),
             )
         }
-        _ => panic!("Codec not supported"),
+        _ => return Err(unsupported_codec()),
     })
 }
 
-/// Parse an UDP teltonika datagram
+/// Parse only the header of a TCP teltonika frame: preamble, data length, codec and first count
 ///
-/// It checks the record counts coincide, parse the whole UDP teltonika channel
-pub fn udp_datagram(input: &[u8]) -> IResult<&[u8], AVLDatagram> {
-    let (input, packet) = length_data(be_u16)(input)?;
-    let (packet, packet_id) = be_u16(packet)?;
-    // Non-usable byte
-    let (packet, _) = tag("\x01")(packet)?;
-    let (packet, avl_packet_id) = be_u8(packet)?;
-    let (packet, imei) = imei(packet)?;
-    let (packet, codec) = codec(packet)?;
-    let (packet, records) = length_count(be_u8, record(codec))(packet)?;
-    let (_packet, _records_count) = verify(be_u8, |number_of_records| {
-        *number_of_records as usize == records.len()
-    })(packet)?;
-
+/// The records are left unparsed and the CRC is not checked, which makes it much cheaper than [`tcp_frame`]
+/// when only routing the frame, see [`FrameHeader::frame_length`] to forward its raw bytes
+pub fn frame_header(input: &[u8]) -> IResult<&[u8], FrameHeader> {
+    let (input, _preamble) = tag("\0\0\0\0")(input)?;
+    let (input, data_length) = be_u32(input)?;
+    let (input, codec) = codec(input)?;
+    let (input, record_count) = record_count(codec)(input)?;
     Ok((
         input,
-        AVLDatagram {
-            packet_id,
-            avl_packet_id,
-            imei,
+        FrameHeader {
+            data_length,
             codec,
-            records,
+            record_count,
         },
     ))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Check the CRC of a TCP teltonika frame without parsing its data field
+///
+/// Returns whether the CRC sent matches the one calculated on the data, along with the bytes following the frame
+pub fn check_crc(input: &[u8]) -> IResult<&[u8], bool> {
+    let (input, _preamble) = tag("\0\0\0\0")(input)?;
+    let (input, data) = length_data(be_u32)(input)?;
+    let (input, crc16) = be_u32(input)?;
+    Ok((input, crc16 == crate::crc16(data) as u32))
+}
 
-    #[test]
-    fn parse_imei() {
-        let input = hex::decode("000F333536333037303432343431303133").unwrap();
-        let (input, imei) = imei(&input).unwrap();
-        assert_eq!(input, &[]);
-        assert_eq!(imei, "356307042441013");
-    }
+/// Compute the IBM CRC of a TCP teltonika frame over each [`CrcRange`], to find the one a device uses
+///
+/// Returns the CRCs in the order of [`CrcRange::ALL`], the one sent by the device is left to compare with.
+/// Only the header is read, the data field is not parsed.
+pub fn crc_candidates(input: &[u8]) -> IResult<&[u8], Vec<(CrcRange, u16)>> {
+    let frame = input;
+    let (input, _preamble) = tag("\0\0\0\0")(input)?;
+    let (input, data) = length_data(be_u32)(input)?;
+    let (rest, _crc16) = be_u32(input)?;
 
-    #[test]
-    fn parse_imei_incomplete() {
-        let input = hex::decode("000F3335363330373034323434313031").unwrap();
-        let err = imei(&input).unwrap_err();
-        assert_ne!(input, &[]);
+    let candidates = CrcRange::ALL
+        .into_iter()
+        .map(|range| {
+            let bytes = match range {
+                CrcRange::Data => data,
+                CrcRange::DataWithoutLastCount => &data[..data.len().saturating_sub(1)],
+                CrcRange::DataWithoutCodec => data.get(1..).unwrap_or_default(),
+                CrcRange::LengthAndData => &frame[4..8 + data.len()],
+                CrcRange::Frame => &frame[..8 + data.len()],
+            };
+            (range, crate::crc16(bytes))
+        })
+        .collect();
+    Ok((rest, candidates))
+}
 
-        if let nom::Err::Incomplete(needed) = err {
-            assert_eq!(
-                needed,
-                nom::Needed::Size(std::num::NonZeroUsize::new(1).unwrap())
-            );
-        } else {
-            panic!("Expected Incomplete error");
+/// Skip an AVL record, reading only the counts needed to find its end
+fn skip_record<'a>(codec: Codec) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], ()> {
+    move |input| {
+        let id_len = if codec == Codec::C8 { 1 } else { 2 };
+        // Timestamp, priority, position, altitude, angle, satellites and speed
+        let (input, _) = take(24usize)(input)?;
+        let (input, _trigger_event_id) = event_id(codec)(input)?;
+        let (input, _generation_type) = cond(codec == Codec::C16, be_u8)(input)?;
+        let (mut input, _ios_count) = event_count(codec)(input)?;
+        for width in [1, 2, 4, 8] {
+            let (rest, count) = event_count(codec)(input)?;
+            let (rest, _) = take(count as usize * (id_len + width))(rest)?;
+            input = rest;
         }
+        if codec == Codec::C8Ext {
+            let (rest, count) = event_count(codec)(input)?;
+            input = rest;
+            for _ in 0..count {
+                let (rest, _id) = event_id(codec)(input)?;
+                let (rest, _value) = length_data(be_u16)(rest)?;
+                input = rest;
+            }
+        }
+        Ok((input, ()))
     }
+}
 
-    #[test]
-    fn parse_codec() {
-        let input = [0x08];
-        let (input, codec) = codec(&input).unwrap();
-        assert_eq!(input, &[]);
-        assert_eq!(codec, Codec::C8);
+/// Compare the header of a TCP teltonika AVL frame with what it holds, see [`FrameDiagnostics`]
+///
+/// Records are skipped instead of being parsed, so a malformed frame can still be inspected.
+/// Fails like [`tcp_frame`] on incomplete inputs and on codecs other than AVL ones.
+pub fn diagnose(input: &[u8]) -> IResult<&[u8], FrameDiagnostics> {
+    let (input, _preamble) = tag("\0\0\0\0")(input)?;
+    let (input, data) = length_data(be_u32)(input)?;
+    let (input, crc16) = be_u32(input)?;
+
+    let (records, codec) = complete(codec)(data)?;
+    if !matches!(codec, Codec::C8 | Codec::C8Ext | Codec::C16) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            data,
+            nom::error::ErrorKind::Switch,
+        )));
     }
+    let (mut rest, declared_records) = complete(record_count(codec))(records)?;
 
-    #[test]
-    fn parse_priority() {
-        let input = [0x00];
-        let (input, priority) = priority(&input).unwrap();
-        assert_eq!(input, &[]);
-        assert_eq!(priority, Priority::Low);
+    // Records are skipped as long as they are followed by at least the second count
+    let mut actual_records = 0;
+    while let Ok((next, ())) = complete(skip_record(codec))(rest) {
+        if next.is_empty() {
+            break;
+        }
+        rest = next;
+        actual_records += 1;
     }
+    // The second count is read only when nothing else follows the records
+    let unread = if rest.len() == codec.record_count_width() {
+        0
+    } else {
+        rest.len()
+    };
 
-    #[test]
-    fn parse_record() {
-        let input = hex::decode("0000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E0000000000000000").unwrap();
-        let (input, record) = record(Codec::C8)(&input).unwrap();
-        assert_eq!(input, &[]);
-        assert_eq!(
-            record,
-            AVLRecord {
-                timestamp: "2019-06-10T10:04:46Z".parse().unwrap(),
-                priority: Priority::High,
-                longitude: 0.0,
-                latitude: 0.0,
-                altitude: 0,
-                angle: 0,
-                satellites: 0,
-                speed: 0,
-                trigger_event_id: 1,
-                generation_type: None,
-                io_events: vec![
-                    AVLEventIO {
-                        id: 21,
-                        value: AVLEventIOValue::U8(3,),
-                    },
-                    AVLEventIO {
-                        id: 1,
-                        value: AVLEventIOValue::U8(1,),
-                    },
-                    AVLEventIO {
-                        id: 66,
-                        value: AVLEventIOValue::U16(24079,),
-                    },
-                    AVLEventIO {
-                        id: 241,
-                        value: AVLEventIOValue::U32(24602,),
-                    },
-                    AVLEventIO {
-                        id: 78,
-                        value: AVLEventIOValue::U64(0,),
-                    },
-                ],
+    Ok((
+        input,
+        FrameDiagnostics {
+            declared_data_len: data.len(),
+            actual_data_len: data.len() - unread,
+            declared_records,
+            actual_records,
+            crc_ok: crc16 == crate::crc16(data) as u32,
+        },
+    ))
+}
+
+/// Parse a TCP teltonika frame from an input known to be complete, e.g. a file replay
+///
+/// Same as [`tcp_frame`], but an input ending before the end of the frame is reported as
+/// [`TeltonikaParseError::Truncated`] instead of [`nom::Err::Incomplete`], as no more bytes will follow.
+///
+/// A zero preamble ending the input is a keep-alive, and a frame declaring an empty data field
+/// is reported as [`TeltonikaParseError::EmptyData`].
+///
+/// A codec that cannot be parsed is reported as [`TeltonikaParseError::UnsupportedCodec`], carrying its raw byte,
+/// and a CRC mismatch as [`TeltonikaParseError::CrcMismatch`], carrying the frame parsed regardless of it
+pub fn tcp_frame_complete(input: &[u8]) -> Result<(&[u8], TeltonikaFrame), TeltonikaParseError> {
+    tcp_frame_complete_with_options(input, &ParseOptions::default())
+}
+
+/// Same as [`tcp_frame_complete`] with custom [`ParseOptions`]
+pub(crate) fn tcp_frame_complete_with_options<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> Result<(&'a [u8], TeltonikaFrame), TeltonikaParseError> {
+    // Preamble, data length, data and CRC
+    let header_len = options.header_len();
+    let declared = || match input.get(4..header_len) {
+        Some(data_length) => {
+            let data_length = data_length
+                .iter()
+                .fold(0, |length, &byte| length << 8 | byte as usize);
+            header_len + data_length + 4
+        }
+        None => header_len,
+    };
+
+    if input == [0x00; 4] {
+        return Ok((&input[4..], TeltonikaFrame::KeepAlive));
+    }
+    match tcp_frame_inner(input, options, true) {
+        Ok(result) => Ok(result),
+        Err(nom::Err::Incomplete(_)) => Err(TeltonikaParseError::Truncated {
+            declared: declared(),
+            available: input.len(),
+        }),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            // Errors within the data field point into a subslice not reaching the end of the input
+            let offset = e.input.as_ptr() as usize - input.as_ptr() as usize;
+            match e.code {
+                nom::error::ErrorKind::Switch if offset == header_len => {
+                    Err(TeltonikaParseError::UnsupportedCodec {
+                        codec: input[header_len],
+                        declared: declared(),
+                    })
+                }
+                // Codec and response count before the type
+                nom::error::ErrorKind::MapRes
+                    if offset == header_len + 2
+                        && matches!(
+                            Codec::try_from(input[header_len]),
+                            Ok(Codec::C12 | Codec::C14)
+                        ) =>
+                {
+                    Err(TeltonikaParseError::UnknownCodec12Type(UnknownCodec12Type(
+                        input[offset],
+                    )))
+                }
+                nom::error::ErrorKind::LengthValue if offset == 4 => {
+                    Err(TeltonikaParseError::EmptyData)
+                }
+                nom::error::ErrorKind::Verify if offset + 4 == declared() => {
+                    // Only the CRC is wrong, the rest of the frame was parsed already
+                    let (_, frame) = tcp_frame_inner(input, options, false)
+                        .expect("the frame parsed up to its CRC");
+                    Err(TeltonikaParseError::CrcMismatch {
+                        frame,
+                        calculated: options.crc.checksum(&input[header_len..offset]),
+                    })
+                }
+                kind => Err(TeltonikaParseError::Invalid { kind, offset }),
             }
-        );
+        }
     }
+}
 
-    #[test]
-    fn parse_record_incomplete() {
-        let input = hex::decode("0000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E00000000000000").unwrap();
-        let err = record(Codec::C8)(&input).unwrap_err();
-        assert_ne!(input, &[]);
-
-        if let nom::Err::Incomplete(needed) = err {
-            assert_eq!(
-                needed,
-                nom::Needed::Size(std::num::NonZeroUsize::new(1).unwrap())
+/// Parse the CRC of a frame, verifying it against the calculated one
+fn frame_crc<'a>(
+    calculated_crc16: u16,
+    check_crc: bool,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], u32> {
+    move |input| {
+        let (rest, crc16) = be_u32(input)?;
+        if check_crc && crc16 != calculated_crc16 as u32 {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                expected = calculated_crc16,
+                received = crc16,
+                "Frame CRC mismatch"
             );
-        } else {
-            panic!("Expected Incomplete error");
+            return Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Verify,
+            )));
         }
+        Ok((rest, crc16))
     }
+}
 
-    #[test]
-    fn parse_frame_codec8_1() {
-        let input = hex::decode("000000000000003608010000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E0000000000000000010000C7CF").unwrap();
-        let (input, frame) = tcp_frame(&input).unwrap();
-        assert_eq!(input, &[]);
-        assert_eq!(
-            frame,
-            TeltonikaFrame::AVL(AVLFrame {
+/// Parse the records of an AVL data field, checking both counts coincide
+fn avl_data<'a, 'o>(
+    codec: Codec,
+    options: &'o ParseOptions,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<AVLRecord>> + 'o {
+    move |data| {
+        let (rest, records_count) = record_count(codec)(data)?;
+        check_limit(data, records_count, options.max_records)?;
+        let (data, records) = count(record(codec, options), records_count)(rest)?;
+        let (data, _records_count) = verify(record_count(codec), |number_of_records| {
+            *number_of_records == records.len()
+        })(data)?;
+        Ok((data, records))
+    }
+}
+
+/// Parse a single Codec 14 command response, the IMEI followed by the response
+fn command_response_imei(input: &[u8]) -> IResult<&[u8], (Imei, CommandResponse)> {
+    let (input, response) = length_data(be_u32)(input)?;
+    let (response, imei) = complete(bcd_imei)(response)?;
+    Ok((input, (imei, response.into())))
+}
+
+/// Parse the data field of a GPRS frame: the command type, the responses and the IMEI of Codec 14
+#[allow(clippy::type_complexity)]
+fn gprs_data(
+    codec: Codec,
+) -> impl FnMut(&[u8]) -> IResult<&[u8], (Codec12Type, Vec<CommandResponse>, Option<Imei>)> {
+    move |data| {
+        let (data, response_qty) = record_count(codec)(data)?;
+
+        let (data, command_type) = codec12_type(data)?;
+        let (data, (responses, imei)) = if codec == Codec::C14 {
+            let (rest, responses) = count(command_response_imei, response_qty)(data)?;
+            let imei = responses.first().map(|(imei, _)| imei.clone());
+            if responses
+                .iter()
+                .any(|(other, _)| Some(other) != imei.as_ref())
+            {
+                return Err(nom::Err::Error(nom::error::Error::new(
+                    data,
+                    nom::error::ErrorKind::Verify,
+                )));
+            }
+            let responses = responses
+                .into_iter()
+                .map(|(_, response)| response)
+                .collect();
+            (rest, (responses, imei))
+        } else {
+            let (data, responses) = count(command_response, response_qty)(data)?;
+            (data, (responses, None))
+        };
+        let (data, _response_qty) = verify(record_count(codec), |number_of_responses| {
+            *number_of_responses == responses.len()
+        })(data)?;
+        Ok((data, (command_type, responses, imei)))
+    }
+}
+
+/// Parse the acknowledgment sent by the server after a TCP frame
+///
+/// A [`u32`] holding the number of records (or command responses) accepted
+pub fn frame_ack(input: &[u8]) -> IResult<&[u8], u32> {
+    be_u32(input)
+}
+
+/// Parse the IMEI approval sent by the server, `0x01` if accepted and `0x00` if not
+pub fn imei_approval(input: &[u8]) -> IResult<&[u8], bool> {
+    map_res(be_u8, |approval| match approval {
+        0x00 => Ok(false),
+        0x01 => Ok(true),
+        _ => Err(()),
+    })(input)
+}
+
+/// Parse a message sent by the server after the IMEI approval, either a frame ACK or a GPRS frame holding commands
+///
+/// An ACK of 0 cannot be told apart from the preamble of a frame by itself,
+/// the bytes are read as a frame only if a whole GPRS frame can be parsed
+pub fn server_message(input: &[u8]) -> IResult<&[u8], ServerMessage> {
+    match tcp_frame(input) {
+        Ok((rest, TeltonikaFrame::GPRS(frame))) => Ok((rest, ServerMessage::Command(frame))),
+        _ => frame_ack(input).map(|(rest, ack)| (rest, ServerMessage::Ack(ack))),
+    }
+}
+
+/// Split the bytes sent by the server to a device into messages, e.g. to replay a recorded connection
+///
+/// The input must start from the beginning of the connection: the IMEI approval, then ACKs and commands.
+/// Iteration stops at the end of the input or at the first bytes that cannot be parsed
+pub fn server_messages(input: &[u8]) -> impl Iterator<Item = ServerMessage> + '_ {
+    let mut input = input;
+    let mut approved = false;
+    std::iter::from_fn(move || {
+        let (rest, message) = if approved {
+            server_message(input).ok()?
+        } else {
+            approved = true;
+            let (rest, approval) = imei_approval(input).ok()?;
+            (rest, ServerMessage::ImeiApproval(approval))
+        };
+        input = rest;
+        Some(message)
+    })
+}
+
+/// Parse an UDP teltonika datagram
+///
+/// It checks the record counts coincide, parse the whole UDP teltonika channel
+pub fn udp_datagram(input: &[u8]) -> IResult<&[u8], AVLDatagram> {
+    udp_datagram_with_options(ParseOptions::default())(input)
+}
+
+/// Parse an UDP teltonika datagram with custom [`ParseOptions`], see [`udp_datagram`]
+///
+/// Datagrams have no CRC and a 2 bytes length, [`ParseOptions::crc`] and [`ParseOptions::u16_data_length`] are ignored.
+pub fn udp_datagram_with_options(
+    options: impl Borrow<ParseOptions>,
+) -> impl Fn(&[u8]) -> IResult<&[u8], AVLDatagram> {
+    move |input| {
+        let (input, packet) = length_data(be_u16)(input)?;
+        // The whole packet is available, running out of it means the datagram is malformed
+        let (_packet, datagram) = complete(udp_packet(options.borrow()))(packet)?;
+        Ok((input, datagram))
+    }
+}
+
+/// Parse an UDP teltonika datagram from an input known to be complete, e.g. a received datagram
+///
+/// Same as [`udp_datagram`], but errors are reported as an owned [`TeltonikaParseError`],
+/// see [`tcp_frame_complete`]
+pub fn udp_datagram_complete(input: &[u8]) -> Result<(&[u8], AVLDatagram), TeltonikaParseError> {
+    match udp_datagram(input) {
+        Ok(result) => Ok(result),
+        Err(nom::Err::Incomplete(_)) => Err(TeltonikaParseError::Truncated {
+            // Length and packet
+            declared: match input.get(..2) {
+                Some(length) => 2 + u16::from_be_bytes(length.try_into().unwrap()) as usize,
+                None => 2,
+            },
+            available: input.len(),
+        }),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(TeltonikaParseError::Invalid {
+            kind: e.code,
+            offset: e.input.as_ptr() as usize - input.as_ptr() as usize,
+        }),
+    }
+}
+
+fn udp_packet<'a>(
+    options: &ParseOptions,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], AVLDatagram> + '_ {
+    move |packet| {
+        let (packet, packet_id) = be_u16(packet)?;
+        // Non-usable byte
+        let (packet, _) = tag("\x01")(packet)?;
+        let (packet, avl_packet_id) = be_u8(packet)?;
+        let (packet, imei) = imei(packet)?;
+        let (packet, codec) = codec(packet)?;
+        let (packet, records) = avl_data(codec, options)(packet)?;
+
+        Ok((
+            packet,
+            AVLDatagram {
+                packet_id,
+                avl_packet_id,
+                imei,
+                codec,
+                records,
+            },
+        ))
+    }
+}
+
+/// Map of the fields of a TCP frame, see [`annotate`]
+struct Annotator<'a> {
+    input: &'a [u8],
+    offset: usize,
+    fields: Vec<(Range<usize>, &'static str)>,
+}
+
+impl Annotator<'_> {
+    /// Labels the next `length` bytes, returning them as a big endian number
+    fn field(&mut self, length: usize, label: &'static str) -> Option<u64> {
+        let bytes = self.input.get(self.offset..self.offset + length)?;
+        self.fields.push((self.offset..self.offset + length, label));
+        self.offset += length;
+        Some(
+            bytes
+                .iter()
+                .take(8)
+                .fold(0, |value, byte| value << 8 | *byte as u64),
+        )
+    }
+
+    fn avl_data(&mut self, codec: Codec) -> Option<()> {
+        let (id_width, count_width) = match codec {
+            Codec::C8 => (1, 1),
+            Codec::C8Ext => (2, 2),
+            _ => (2, 1),
+        };
+
+        let records = self.field(1, "number of records")?;
+        for _ in 0..records {
+            self.field(8, "timestamp")?;
+            self.field(1, "priority")?;
+            self.field(4, "longitude")?;
+            self.field(4, "latitude")?;
+            self.field(2, "altitude")?;
+            self.field(2, "angle")?;
+            self.field(1, "satellites")?;
+            self.field(2, "speed")?;
+            self.field(id_width, "event id")?;
+            if codec == Codec::C16 {
+                self.field(1, "generation type")?;
+            }
+            self.field(count_width, "io count")?;
+            for value_width in [1, 2, 4, 8] {
+                let events = self.field(count_width, "io group count")?;
+                for _ in 0..events {
+                    self.field(id_width, "io id")?;
+                    self.field(value_width, "io value")?;
+                }
+            }
+            if codec == Codec::C8Ext {
+                let events = self.field(count_width, "io group count")?;
+                for _ in 0..events {
+                    self.field(id_width, "io id")?;
+                    let length = self.field(2, "io length")?;
+                    self.field(length as usize, "io value")?;
+                }
+            }
+        }
+        self.field(1, "number of records")?;
+        Some(())
+    }
+
+    fn gprs_data(&mut self, codec: Codec) -> Option<()> {
+        let responses = self.field(1, "command quantity")?;
+        self.field(1, "command type")?;
+        for _ in 0..responses {
+            let mut length = self.field(4, "command size")? as usize;
+            // Codec 14 prefixes each response with the IMEI, counted in its size
+            if codec == Codec::C14 {
+                self.field(8, "imei")?;
+                length = length.saturating_sub(8);
+            }
+            self.field(length, "command")?;
+        }
+        self.field(1, "command quantity")?;
+        Some(())
+    }
+
+    fn tcp_frame(&mut self) -> Option<()> {
+        self.field(4, "preamble")?;
+        self.field(4, "data length")?;
+        let codec = self.field(1, "codec")? as u8;
+        match codec {
+            0x08 => self.avl_data(Codec::C8)?,
+            0x8E => self.avl_data(Codec::C8Ext)?,
+            0x10 => self.avl_data(Codec::C16)?,
+            0x0C => self.gprs_data(Codec::C12)?,
+            0x0E => self.gprs_data(Codec::C14)?,
+            _ => return None,
+        }
+        self.field(4, "crc")?;
+        Some(())
+    }
+}
+
+/// Best effort map of which field each byte of a TCP frame belongs to, for debugging malformed frames
+///
+/// Fields are labeled in order while the bytes are enough to read them,
+/// nothing is validated (preamble, counts and CRC included).
+/// The bytes left once a field cannot be read, or after an unsupported codec, are labeled as `"unparsed"`
+///
+/// ```rust
+/// let input = hex::decode("000000000000000F0C010500000007676574696E666F0100004312").unwrap();
+/// let fields = nom_teltonika::parser::annotate(&input);
+///
+/// assert_eq!(fields[0], (0..4, "preamble"));
+/// assert_eq!(fields[6], (15..22, "command"));
+/// ```
+pub fn annotate(input: &[u8]) -> Vec<(Range<usize>, &'static str)> {
+    let mut annotator = Annotator {
+        input,
+        offset: 0,
+        fields: vec![],
+    };
+    annotator.tcp_frame();
+    if annotator.offset < input.len() {
+        annotator
+            .fields
+            .push((annotator.offset..input.len(), "unparsed"));
+    }
+    annotator.fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use crate::{ParsedVariable, VariableParseError};
+
+    #[test]
+    fn parse_imei() {
+        let input = hex::decode("000F333536333037303432343431303133").unwrap();
+        let (input, imei) = imei(&input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(imei, "356307042441013");
+    }
+
+    #[test]
+    fn parse_imei_invalid() {
+        let input = hex::decode("000F333536333037303432343431303141").unwrap();
+        let err = imei(&input).unwrap_err();
+
+        if let nom::Err::Error(e) = err {
+            assert_eq!(e.code, nom::error::ErrorKind::MapRes);
+        } else {
+            panic!("Expected Error");
+        }
+    }
+
+    #[test]
+    fn parse_imei_incomplete() {
+        let input = hex::decode("000F3335363330373034323434313031").unwrap();
+        let err = imei(&input).unwrap_err();
+        assert!(!input.is_empty());
+
+        if let nom::Err::Incomplete(needed) = err {
+            assert_eq!(
+                needed,
+                nom::Needed::Size(std::num::NonZeroUsize::new(1).unwrap())
+            );
+        } else {
+            panic!("Expected Incomplete error");
+        }
+    }
+
+    #[test]
+    fn parse_codec() {
+        let input = [0x08];
+        let (input, codec) = codec(&input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(codec, Codec::C8);
+    }
+
+    #[test]
+    fn priority_round_trip() {
+        for value in 0x00..=0x02 {
+            assert_eq!(Priority::try_from(value).map(u8::from), Ok(value));
+        }
+        assert_eq!(Priority::try_from(0x07), Err(UnknownPriority(0x07)));
+    }
+
+    #[test]
+    fn event_generation_cause_round_trip() {
+        for value in 0..=7 {
+            assert_eq!(
+                EventGenerationCause::try_from(value).map(u8::from),
+                Ok(value)
+            );
+        }
+        assert_eq!(
+            EventGenerationCause::try_from(8),
+            Err(UnknownEventGenerationCause(8))
+        );
+    }
+
+    #[test]
+    fn parse_priority() {
+        let input = [0x00];
+        let (input, priority) = priority(&input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(priority, Priority::Low);
+    }
+
+    #[test]
+    fn parse_record() {
+        let input = hex::decode("0000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E0000000000000000").unwrap();
+        let (input, record) = record(Codec::C8, &ParseOptions::default())(&input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(
+            record,
+            AVLRecord {
+                timestamp: "2019-06-10T10:04:46Z".parse().unwrap(),
+                priority: Priority::High,
+                longitude: 0.0,
+                latitude: 0.0,
+                longitude_raw: 0,
+                latitude_raw: 0,
+                altitude: 0,
+                angle: 0,
+                satellites: 0,
+                speed: 0,
+                trigger_event_id: 1,
+                generation_type: None,
+                io_events: vec![
+                    AVLEventIO {
+                        id: 21,
+                        value: AVLEventIOValue::U8(3,),
+                    },
+                    AVLEventIO {
+                        id: 1,
+                        value: AVLEventIOValue::U8(1,),
+                    },
+                    AVLEventIO {
+                        id: 66,
+                        value: AVLEventIOValue::U16(24079,),
+                    },
+                    AVLEventIO {
+                        id: 241,
+                        value: AVLEventIOValue::U32(24602,),
+                    },
+                    AVLEventIO {
+                        id: 78,
+                        value: AVLEventIOValue::U64(0,),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_record_incomplete() {
+        let input = hex::decode("0000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E00000000000000").unwrap();
+        let err = record(Codec::C8, &ParseOptions::default())(&input).unwrap_err();
+        assert!(!input.is_empty());
+
+        if let nom::Err::Incomplete(needed) = err {
+            assert_eq!(
+                needed,
+                nom::Needed::Size(std::num::NonZeroUsize::new(1).unwrap())
+            );
+        } else {
+            panic!("Expected Incomplete error");
+        }
+    }
+
+    #[test]
+    fn parse_frame_codec8_1() {
+        let input = hex::decode(CODEC8_FRAME_1).unwrap();
+        let (input, frame) = tcp_frame(&input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(
+            frame,
+            TeltonikaFrame::AVL(AVLFrame {
                 codec: Codec::C8,
                 records: vec![AVLRecord {
                     timestamp: "2019-06-10T10:04:46Z".parse().unwrap(),
                     priority: Priority::High,
                     longitude: 0.0,
                     latitude: 0.0,
+                    longitude_raw: 0,
+                    latitude_raw: 0,
                     altitude: 0,
                     angle: 0,
                     satellites: 0,
@@ -423,9 +1367,9 @@ mod tests {
 
     #[test]
     fn parse_frame_codec8_2() {
-        let input = hex::decode("000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
         let (input, frame) = tcp_frame(&input).unwrap();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(
             frame,
             TeltonikaFrame::AVL(AVLFrame {
@@ -435,6 +1379,8 @@ mod tests {
                     priority: Priority::High,
                     longitude: 0.0,
                     latitude: 0.0,
+                    longitude_raw: 0,
+                    latitude_raw: 0,
                     altitude: 0,
                     angle: 0,
                     satellites: 0,
@@ -463,9 +1409,9 @@ mod tests {
 
     #[test]
     fn parse_frame_codec8_3() {
-        let input = hex::decode("000000000000004308020000016B40D57B480100000000000000000000000000000001010101000000000000016B40D5C198010000000000000000000000000000000101010101000000020000252C").unwrap();
+        let input = hex::decode(CODEC8_FRAME_3).unwrap();
         let (input, frame) = tcp_frame(&input).unwrap();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(
             frame,
             TeltonikaFrame::AVL(AVLFrame {
@@ -476,6 +1422,8 @@ mod tests {
                         priority: Priority::High,
                         longitude: 0.0,
                         latitude: 0.0,
+                        longitude_raw: 0,
+                        latitude_raw: 0,
                         altitude: 0,
                         angle: 0,
                         satellites: 0,
@@ -492,6 +1440,8 @@ mod tests {
                         priority: Priority::High,
                         longitude: 0.0,
                         latitude: 0.0,
+                        longitude_raw: 0,
+                        latitude_raw: 0,
                         altitude: 0,
                         angle: 0,
                         satellites: 0,
@@ -511,9 +1461,9 @@ mod tests {
 
     #[test]
     fn parse_frame_codec8ext() {
-        let input = hex::decode("000000000000004A8E010000016B412CEE000100000000000000000000000000000000010005000100010100010011001D00010010015E2C880002000B000000003544C87A000E000000001DD7E06A00000100002994").unwrap();
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
         let (input, frame) = tcp_frame(&input).unwrap();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(
             frame,
             TeltonikaFrame::AVL(AVLFrame {
@@ -523,6 +1473,8 @@ mod tests {
                     priority: Priority::High,
                     longitude: 0.0,
                     latitude: 0.0,
+                    longitude_raw: 0,
+                    latitude_raw: 0,
                     altitude: 0,
                     angle: 0,
                     satellites: 0,
@@ -559,9 +1511,9 @@ mod tests {
 
     #[test]
     fn parse_frame_codec16() {
-        let input = hex::decode("000000000000005F10020000016BDBC7833000000000000000000000000000000000000B05040200010000030002000B00270042563A00000000016BDBC7871800000000000000000000000000000000000B05040200010000030002000B00260042563A00000200005FB3").unwrap();
+        let input = hex::decode(CODEC16_FRAME).unwrap();
         let (input, frame) = tcp_frame(&input).unwrap();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(
             frame,
             TeltonikaFrame::AVL(AVLFrame {
@@ -572,6 +1524,8 @@ mod tests {
                         priority: Priority::Low,
                         longitude: 0.0,
                         latitude: 0.0,
+                        longitude_raw: 0,
+                        latitude_raw: 0,
                         altitude: 0,
                         angle: 0,
                         satellites: 0,
@@ -602,6 +1556,8 @@ mod tests {
                         priority: Priority::Low,
                         longitude: 0.0,
                         latitude: 0.0,
+                        longitude_raw: 0,
+                        latitude_raw: 0,
                         altitude: 0,
                         angle: 0,
                         satellites: 0,
@@ -637,19 +1593,21 @@ mod tests {
     fn parse_udp_datagram() {
         let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
         let (input, datagram) = udp_datagram(&input).unwrap();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(
             datagram,
             AVLDatagram {
                 packet_id: 0xCAFE,
                 avl_packet_id: 0x05,
-                imei: String::from("\x33\x35\x32\x30\x39\x33\x30\x38\x36\x34\x30\x33\x36\x35\x35"),
+                imei: Imei::parse("352093086403655").unwrap(),
                 codec: Codec::C8,
                 records: vec![AVLRecord {
                     timestamp: "2019-06-13T06:23:26Z".parse().unwrap(),
                     priority: Priority::High,
                     longitude: 0.0,
                     latitude: 0.0,
+                    longitude_raw: 0,
+                    latitude_raw: 0,
                     altitude: 0,
                     angle: 0,
                     satellites: 0,
@@ -679,7 +1637,7 @@ mod tests {
     fn parse_udp_datagram_incomplete() {
         let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC00").unwrap();
         let err = udp_datagram(&input).unwrap_err();
-        assert_ne!(input, &[]);
+        assert!(!input.is_empty());
 
         if let nom::Err::Incomplete(needed) = err {
             assert_eq!(
@@ -693,12 +1651,14 @@ mod tests {
 
     #[test]
     fn parse_negative_emisphere_coordinates() {
-        let input = hex::decode("00000000000000460801000001776D58189001FA0A1F00F1194D80009C009D05000F9B0D06EF01F0001505C80045019B0105B5000BB6000A424257430F8044000002F1000060191000000BE1000100006E2B").unwrap();
+        let input = hex::decode(NEGATIVE_COORDINATES_FRAME).unwrap();
         let (input, frame) = tcp_frame(&input).unwrap();
         let frame = frame.unwrap_avl();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(frame.records[0].longitude, -10.0);
         assert_eq!(frame.records[0].latitude, -25.0);
+        assert_eq!(frame.records[0].longitude_raw, -100000000);
+        assert_eq!(frame.records[0].latitude_raw, -250000000);
     }
 
     #[test]
@@ -706,8 +1666,17 @@ mod tests {
         let input = hex::decode("00000000000000900C010600000088494E493A323031392F372F323220373A3232205254433A323031392F372F323220373A3533205253543A32204552523A312053523A302042523A302043463A302046473A3020464C3A302054553A302F302055543A3020534D533A30204E4F4750533A303A3330204750533A31205341543A302052533A332052463A36352053463A31204D443A30010000C78F").unwrap();
         let (input, frame) = tcp_frame(&input).unwrap();
         let frame = frame.unwrap_gprs();
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
         assert_eq!(&frame.command_responses[0], "INI:2019/7/22 7:22 RTC:2019/7/22 7:53 RST:2 ERR:1 SR:0 BR:0 CF:0 FG:0 FL:0 TU:0/0 UT:0 SMS:0 NOGPS:0:30 GPS:1 SAT:0 RS:3 RF:65 SF:1 MD:0");
+
+        let info = parse_getinfo(frame.command_responses[0].as_str_strict().unwrap());
+        assert_eq!(info.len(), 19);
+        assert_eq!(info["INI"], "2019/7/22 7:22");
+        assert_eq!(info["RTC"], "2019/7/22 7:53");
+        assert_eq!(info["TU"], "0/0");
+        assert_eq!(info["NOGPS"], "0:30");
+        assert_eq!(info["SAT"], "0");
+        assert_eq!(info["MD"], "0");
     }
 
     #[test]
@@ -716,10 +1685,998 @@ mod tests {
         let (input, frame) = tcp_frame(&input).unwrap();
         let frame = frame.unwrap_gprs();
 
-        assert_eq!(input, &[]);
+        assert!(input.is_empty());
+        assert_eq!(frame.command_type, Codec12Type::Response);
         assert_eq!(
             &frame.command_responses[0],
             "DI1:1 DI2:0 DI3:0 AIN1:0 AIN2:16924 DO1:0 DO2:1"
         );
     }
+
+    #[test]
+    fn parse_binary_command_response() {
+        let input = hex::decode("000000000000000B0C01060000000341FF4201000059D4").unwrap();
+        let (input, frame) = tcp_frame(&input).unwrap();
+        let response = &frame.unwrap_gprs().command_responses[0];
+
+        assert!(input.is_empty());
+        assert_eq!(response.as_bytes(), [0x41, 0xFF, 0x42]);
+        assert!(response.as_str_strict().is_err());
+        assert_eq!(response.as_str_lossy(), "A\u{FFFD}B");
+    }
+
+    #[test]
+    fn parse_utf8_command_response() {
+        let input = hex::decode("000000000000000A0C010600000002C3A90100009103").unwrap();
+        let (input, frame) = tcp_frame(&input).unwrap();
+        let response = &frame.unwrap_gprs().command_responses[0];
+
+        assert!(input.is_empty());
+        assert_eq!(response.as_str_strict(), Ok("é"));
+        assert_eq!(response, "é");
+    }
+
+    #[test]
+    fn parse_codec12_type() {
+        assert_eq!(codec12_type(&[0x05]), Ok((&[][..], Codec12Type::Command)));
+        assert_eq!(codec12_type(&[0x06]), Ok((&[][..], Codec12Type::Response)));
+        assert_eq!(
+            codec12_type(&[0x07]),
+            Err(nom::Err::Error(nom::error::Error::new(
+                &[0x07][..],
+                nom::error::ErrorKind::MapRes
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_command_codec12() {
+        // getinfo
+        let input = hex::decode("000000000000000F0C010500000007676574696E666F0100004312").unwrap();
+        let (input, frame) = tcp_frame(&input).unwrap();
+        let frame = frame.unwrap_gprs();
+
+        assert!(input.is_empty());
+        assert_eq!(frame.command_type, Codec12Type::Command);
+        assert_eq!(frame.command_responses, vec!["getinfo"]);
+    }
+
+    #[test]
+    fn parse_command_response_codec12_invalid_type() {
+        let input = hex::decode("00000000000000370C01070000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3").unwrap();
+        let err = tcp_frame(&input).unwrap_err();
+
+        assert_eq!(
+            err,
+            nom::Err::Error(nom::error::Error::new(
+                &input[10..input.len() - 4],
+                nom::error::ErrorKind::MapRes
+            ))
+        );
+        assert_eq!(
+            tcp_frame_complete(&input),
+            Err(TeltonikaParseError::UnknownCodec12Type(UnknownCodec12Type(
+                0x07
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_records_callback() {
+        let input = hex::decode(CODEC8_FRAME_3).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let frame = frame.unwrap_avl();
+
+        // Skip preamble, data length and codec
+        let data = &input[9..input.len() - 4];
+        let mut records = vec![];
+        let (rest, count) = parse_records(data, Codec::C8, |record| records.push(record)).unwrap();
+
+        assert!(rest.is_empty());
+        assert_eq!(count, 2);
+        assert_eq!(records, frame.records);
+    }
+
+    #[test]
+    fn annotate_frame_codec8ext() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        let fields = annotate(&input);
+
+        assert_eq!(fields[0], (0..4, "preamble"));
+        assert_eq!(fields[1], (4..8, "data length"));
+        assert_eq!(fields[2], (8..9, "codec"));
+        assert_eq!(fields[3], (9..10, "number of records"));
+        assert_eq!(fields[4], (10..18, "timestamp"));
+        assert_eq!(fields[12], (34..36, "event id"));
+        assert_eq!(fields[13], (36..38, "io count"));
+        assert_eq!(fields.last(), Some(&(input.len() - 4..input.len(), "crc")));
+        assert!(fields.windows(2).all(|w| w[0].0.end == w[1].0.start));
+    }
+
+    #[test]
+    fn annotate_truncated_frame() {
+        let input = hex::decode("000000000000004A8E010000016B412C").unwrap();
+        let fields = annotate(&input);
+
+        assert_eq!(fields[3], (9..10, "number of records"));
+        assert_eq!(fields.last(), Some(&(10..input.len(), "unparsed")));
+    }
+
+    #[test]
+    fn annotate_frame_codec14() {
+        let input = hex::decode(
+            "00000000000000190E01060000001103520930814522515665723A30332E3138010000AA2D",
+        )
+        .unwrap();
+        let fields = annotate(&input);
+
+        assert_eq!(fields[2], (8..9, "codec"));
+        assert_eq!(fields[5], (11..15, "command size"));
+        assert_eq!(fields[6], (15..23, "imei"));
+        assert_eq!(fields[7], (23..32, "command"));
+        assert_eq!(fields[8], (32..33, "command quantity"));
+        assert_eq!(fields.last(), Some(&(33..37, "crc")));
+        assert_eq!(fields.len(), 10);
+    }
+
+    #[test]
+    fn annotate_unknown_codec() {
+        let input = hex::decode("00000000000000020F00").unwrap();
+        let fields = annotate(&input);
+
+        assert_eq!(fields[2], (8..9, "codec"));
+        assert_eq!(fields[3], (9..10, "unparsed"));
+    }
+
+    #[test]
+    fn parse_frame_data_length_zero() {
+        // A zero data length on 4 bytes is another preamble, it shows on 2 bytes followed by a non zero CRC
+        let input = hex::decode("000000000000FFFFFFFF").unwrap();
+        let options = ParseOptions {
+            u16_data_length: true,
+            ..Default::default()
+        };
+        let err = tcp_frame_with_options(&options)(&input).unwrap_err();
+
+        assert_eq!(
+            err,
+            nom::Err::Error(nom::error::Error::new(
+                &input[4..],
+                nom::error::ErrorKind::LengthValue
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_frame_data_longer_than_content() {
+        let mut input = hex::decode(CODEC8_FRAME_2).unwrap();
+        // One more byte after the second record count
+        input[7] += 1;
+        input.insert(48, 0xAA);
+        let err = tcp_frame(&input).unwrap_err();
+
+        assert_eq!(
+            err,
+            nom::Err::Error(nom::error::Error::new(
+                &input[48..49],
+                nom::error::ErrorKind::Eof
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_keep_alive() {
+        let mut input = hex::decode("00000000").unwrap();
+        assert!(matches!(tcp_frame(&input), Err(nom::Err::Incomplete(_))));
+        assert_eq!(
+            tcp_frame_complete(&input),
+            Ok((&[][..], TeltonikaFrame::KeepAlive))
+        );
+
+        input.extend(hex::decode(CODEC8_FRAME_2).unwrap());
+        let (rest, frame) = tcp_frame(&input).unwrap();
+        assert_eq!(frame, TeltonikaFrame::KeepAlive);
+        assert_eq!(rest, &input[4..]);
+
+        let (rest, frame) = tcp_frame(rest).unwrap();
+        assert!(matches!(frame, TeltonikaFrame::AVL(_)));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn parse_keep_alives_before_frame() {
+        let mut input = vec![0x00; 8];
+        input.extend(hex::decode(CODEC8_FRAME_2).unwrap());
+
+        let (rest, frame) = tcp_frame(&input).unwrap();
+        assert_eq!(frame, TeltonikaFrame::KeepAlive);
+        let (rest, frame) = tcp_frame(rest).unwrap();
+        assert_eq!(frame, TeltonikaFrame::KeepAlive);
+        let (rest, frame) = tcp_frame(rest).unwrap();
+        assert!(matches!(frame, TeltonikaFrame::AVL(_)));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn parse_frame_data_length_too_short() {
+        // Data length of 0x20 while the data is 0x28 bytes long
+        let input = hex::decode("000000000000002008010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+        let err = tcp_frame(&input).unwrap_err();
+
+        assert!(matches!(
+            err,
+            nom::Err::Error(nom::error::Error {
+                code: nom::error::ErrorKind::Complete,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_frame_data_length_overrun() {
+        let input = hex::decode("00000000FFFFFFFF08010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+        let err = tcp_frame(&input).unwrap_err();
+
+        assert!(matches!(err, nom::Err::Incomplete(_)));
+    }
+
+    #[test]
+    fn parse_udp_datagram_length_too_short() {
+        let input = hex::decode("0010CAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+        let err = udp_datagram(&input).unwrap_err();
+
+        assert!(matches!(
+            err,
+            nom::Err::Error(nom::error::Error {
+                code: nom::error::ErrorKind::Complete,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_frame_complete_truncated() {
+        let input = hex::decode("000000000000003608010000016B40D8EA30010000000000000000000000000000000105021503010101425E0F01F10000601A014E000000000000").unwrap();
+        assert_eq!(
+            tcp_frame_complete(&input),
+            Err(TeltonikaParseError::Truncated {
+                declared: 66,
+                available: 59
+            })
+        );
+        assert_eq!(
+            tcp_frame_complete(&input[..6]),
+            Err(TeltonikaParseError::Truncated {
+                declared: 8,
+                available: 6
+            })
+        );
+    }
+
+    #[test]
+    fn parse_frame_complete() {
+        let input = hex::decode(CODEC8_FRAME_1).unwrap();
+        let (rest, frame) = tcp_frame_complete(&input).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(frame.unwrap_avl().records.len(), 1);
+
+        let mut corrupted = input.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] = 0xCE;
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut expected = frame.clone().unwrap_avl();
+        expected.crc16 = 0xC7CE;
+        assert_eq!(
+            tcp_frame_complete(&corrupted),
+            Err(TeltonikaParseError::CrcMismatch {
+                frame: TeltonikaFrame::AVL(expected),
+                calculated: 0xC7CF,
+            })
+        );
+
+        // Corrupting the record count is not a CRC mismatch
+        let mut corrupted = input.clone();
+        corrupted[last - 4] = 0x02;
+        assert!(matches!(
+            tcp_frame_complete(&corrupted),
+            Err(TeltonikaParseError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_frame_header() {
+        let input = hex::decode(CODEC8_FRAME_3).unwrap();
+        let (rest, header) = frame_header(&input).unwrap();
+
+        assert_eq!(
+            header,
+            FrameHeader {
+                data_length: 0x43,
+                codec: Codec::C8,
+                record_count: 2,
+            }
+        );
+        assert_eq!(rest.len(), input.len() - 10);
+        assert_eq!(header.frame_length(), input.len());
+    }
+
+    #[test]
+    fn parse_frame_ccitt_crc() {
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+        let data = &input[8..input.len() - 4];
+        let ccitt = crate::crc16_ccitt(data);
+        assert_ne!(ccitt, crate::crc16(data));
+
+        let mut ccitt_input = input[..input.len() - 4].to_vec();
+        ccitt_input.extend((ccitt as u32).to_be_bytes());
+
+        let options = ParseOptions {
+            crc: CrcAlgorithm::Ccitt,
+            ..Default::default()
+        };
+        let (_, frame) = tcp_frame_with_options(&options)(&ccitt_input).unwrap();
+        assert_eq!(frame.unwrap_avl().crc16, ccitt as u32);
+
+        // Each algorithm rejects the other one CRC
+        assert!(tcp_frame(&ccitt_input).is_err());
+        assert!(tcp_frame_with_options(options)(&input).is_err());
+    }
+
+    #[test]
+    fn parse_frame_variable_parsers() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        let mut frame = tcp_frame(&input).unwrap().1.unwrap_avl();
+        frame.records[0].io_events.push(AVLEventIO::new(
+            385,
+            AVLEventIOValue::Variable(vec![1, 2, 3]),
+        ));
+        let input = frame.to_bytes().unwrap();
+
+        // Without a parser the value stays raw
+        let (_, parsed) = tcp_frame(&input).unwrap();
+        assert!(parsed.unwrap_avl().payload_eq(&frame));
+
+        let mut options = ParseOptions::default();
+        options.variable_parsers.register(385, |bytes| {
+            Ok(ParsedVariable::List(
+                bytes
+                    .iter()
+                    .map(|byte| ParsedVariable::Unsigned(*byte as u64))
+                    .collect(),
+            ))
+        });
+        let (_, parsed) = tcp_frame_with_options(options)(&input).unwrap();
+        let parsed = parsed.unwrap_avl();
+        assert_eq!(
+            parsed.records[0].io_events.last().unwrap().value,
+            AVLEventIOValue::Parsed {
+                raw: vec![1, 2, 3],
+                value: ParsedVariable::List(vec![
+                    ParsedVariable::Unsigned(1),
+                    ParsedVariable::Unsigned(2),
+                    ParsedVariable::Unsigned(3),
+                ]),
+            }
+        );
+        // Parsed values are encoded back as their raw bytes
+        assert_eq!(parsed.to_bytes().unwrap(), input);
+    }
+
+    #[test]
+    fn parse_frame_variable_parser_error() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        let mut frame = tcp_frame(&input).unwrap().1.unwrap_avl();
+        frame.records[0].io_events.push(AVLEventIO::new(
+            385,
+            AVLEventIOValue::Variable(vec![1, 2, 3]),
+        ));
+        let input = frame.to_bytes().unwrap();
+
+        let mut options = ParseOptions::default();
+        options
+            .variable_parsers
+            .register(385, |_| Err(VariableParseError("odd length".into())));
+        let (_, parsed) = tcp_frame_with_options(options)(&input).unwrap();
+        let parsed = parsed.unwrap_avl();
+        // The raw bytes are kept next to the error
+        assert_eq!(
+            parsed.records[0].io_events.last().unwrap().value,
+            AVLEventIOValue::ParseFailed {
+                raw: vec![1, 2, 3],
+                error: VariableParseError("odd length".into()),
+            }
+        );
+        assert_eq!(parsed.to_bytes().unwrap(), input);
+    }
+
+    #[test]
+    fn parse_frame_unsupported_codec() {
+        // Unknown codec, then Codec 13
+        for input in [
+            "000000000000000F99010500000007676574696E666F0100004312",
+            "00000000000000130D01050000000B60C21CC1676574696E666F0100003DA9",
+        ] {
+            let input = hex::decode(input).unwrap();
+            assert_eq!(
+                tcp_frame(&input),
+                Err(nom::Err::Error(nom::error::Error::new(
+                    &input[8..input.len() - 4],
+                    nom::error::ErrorKind::Switch
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_frame_complete_unsupported_codec() {
+        let input = hex::decode("000000000000000F99010500000007676574696E666F0100004312").unwrap();
+        assert_eq!(
+            tcp_frame_complete(&input),
+            Err(TeltonikaParseError::UnsupportedCodec {
+                codec: 0x99,
+                declared: input.len()
+            })
+        );
+
+        let input =
+            hex::decode("00000000000000130D01050000000B60C21CC1676574696E666F0100003DA9").unwrap();
+        assert_eq!(
+            tcp_frame_complete(&input),
+            Err(TeltonikaParseError::UnsupportedCodec {
+                codec: 0x0D,
+                declared: input.len()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_server_messages() {
+        let input = hex::decode(concat!(
+            "01",
+            "00000001",
+            "000000000000000F0C010500000007676574696E666F0100004312",
+            "00000000",
+            "00000002",
+        ))
+        .unwrap();
+        let messages: Vec<ServerMessage> = server_messages(&input).collect();
+
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0], ServerMessage::ImeiApproval(true));
+        assert_eq!(messages[1], ServerMessage::Ack(1));
+        match &messages[2] {
+            ServerMessage::Command(frame) => {
+                assert_eq!(frame.command_type, Codec12Type::Command);
+                assert_eq!(frame.command_responses, vec!["getinfo"]);
+            }
+            message => panic!("Unexpected message: {message:?}"),
+        }
+        assert_eq!(messages[3], ServerMessage::Ack(0));
+        assert_eq!(messages[4], ServerMessage::Ack(2));
+
+        // Iteration stops at the truncated ACK
+        assert_eq!(server_messages(&input[..input.len() - 1]).count(), 4);
+    }
+
+    #[test]
+    fn parse_frame_empty_io_groups() {
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+        let template = tcp_frame(&input).unwrap().1.unwrap_avl();
+
+        let values = [
+            AVLEventIOValue::U8(0x12),
+            AVLEventIOValue::U16(0x1234),
+            AVLEventIOValue::U32(0x12345678),
+            AVLEventIOValue::U64(0x123456789ABCDEF0),
+            AVLEventIOValue::Variable(vec![0x12, 0x34]),
+        ];
+        for codec in [Codec::C8, Codec::C8Ext, Codec::C16] {
+            let groups = if codec == Codec::C8Ext { 5 } else { 4 };
+            // Only one group filled, then only one group empty
+            let cases =
+                (0..groups)
+                    .map(|group| vec![values[group].clone()])
+                    .chain((0..groups).map(|empty| {
+                        (0..groups)
+                            .filter(|group| *group != empty)
+                            .map(|group| values[group].clone())
+                            .collect()
+                    }));
+            for case in cases {
+                let mut frame = template.clone();
+                frame.codec = codec;
+                frame.records[0].generation_type =
+                    (codec == Codec::C16).then_some(EventGenerationCause::OnChange);
+                frame.records[0].io_events = case
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, value)| AVLEventIO::new(id as u16 + 1, value))
+                    .collect();
+                let events = &frame.records[0].io_events;
+                let input = frame.to_bytes().unwrap();
+
+                let (rest, parsed) = tcp_frame(&input).unwrap();
+                assert!(rest.is_empty());
+                assert!(parsed.unwrap_avl().payload_eq(&frame));
+
+                // Every prefix, including the ones ending on an empty group count, needs more bytes
+                for end in 0..input.len() {
+                    assert!(
+                        matches!(tcp_frame(&input[..end]), Err(nom::Err::Incomplete(_))),
+                        "{codec:?} {events:?} split at {end}"
+                    );
+                }
+
+                // Same for the records parsed on their own
+                let data = &input[10..input.len() - 5];
+                let (rest, record) = record(codec, &ParseOptions::default())(data).unwrap();
+                assert!(rest.is_empty());
+                assert_eq!(record, frame.records[0]);
+                for end in 0..data.len() {
+                    assert!(
+                        matches!(
+                            record_raw(codec)(&data[..end]),
+                            Err(nom::Err::Incomplete(_))
+                        ),
+                        "{codec:?} {events:?} raw split at {end}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_command_response_codec14() {
+        let input = hex::decode(
+            "00000000000000190E01060000001103520930814522515665723A30332E3138010000AA2D",
+        )
+        .unwrap();
+        let (rest, frame) = tcp_frame(&input).unwrap();
+        assert!(rest.is_empty());
+        let frame = frame.unwrap_gprs();
+        assert_eq!(frame.codec, Codec::C14);
+        assert_eq!(frame.command_type, Codec12Type::Response);
+        assert_eq!(frame.command_responses, vec!["Ver:03.18"]);
+        assert_eq!(frame.imei, Some(Imei::parse("352093081452251").unwrap()));
+
+        // IMEI mismatch, only the IMEI of the device is sent back
+        let input =
+            hex::decode("00000000000000100E011100000008035209308145225101000032AC").unwrap();
+        let frame = tcp_frame(&input).unwrap().1.unwrap_gprs();
+        assert_eq!(frame.command_type, Codec12Type::Nack);
+        assert_eq!(frame.command_responses, vec![""]);
+        assert_eq!(frame.imei, Some(Imei::parse("352093081452251").unwrap()));
+    }
+
+    #[test]
+    fn parse_command_response_codec14_imei_mismatch() {
+        let mut data = vec![0x0E, 0x02, 0x06];
+        for imei in ["352093081452251", "356307042441013"] {
+            data.extend(17u32.to_be_bytes());
+            data.extend(Imei::parse(imei).unwrap().to_bcd());
+            data.extend(b"Ver:03.18");
+        }
+        data.push(0x02);
+        let mut input = vec![0x00; 4];
+        input.extend((data.len() as u32).to_be_bytes());
+        input.extend(&data);
+        input.extend((crate::crc16(&data) as u32).to_be_bytes());
+
+        assert_eq!(
+            tcp_frame(&input),
+            Err(nom::Err::Error(nom::error::Error::new(
+                &input[11..input.len() - 4],
+                nom::error::ErrorKind::Verify
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_frame_bare_event() {
+        // Movement event (240) without any IO value
+        let input = hex::decode(BARE_EVENT_FRAME).unwrap();
+        let (rest, frame) = tcp_frame(&input).unwrap();
+        assert!(rest.is_empty());
+
+        let record = &frame.unwrap_avl().records[0];
+        assert_eq!(record.trigger_event_id, 240);
+        assert!(record.io_events.is_empty());
+        assert!(record.is_bare_event());
+
+        // Records with IO values or without trigger event are not bare events
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let mut record = frame.unwrap_avl().records.remove(0);
+        assert!(!record.is_bare_event());
+        record.io_events.clear();
+        assert!(record.is_bare_event());
+        record.trigger_event_id = 0;
+        assert!(!record.is_bare_event());
+    }
+
+    #[test]
+    fn parse_frame_codec16_field_order() {
+        // Codec 16 reads the 2 bytes event id, then the generation type, then the 1 byte IO count,
+        // each IO group being a 1 byte count followed by 2 bytes ids and their values
+        let input = hex::decode(concat!(
+            "00000000",         // preamble
+            "0000003A",         // data length
+            "10",               // codec 16
+            "01",               // number of records
+            "0000016B40D9AD80", // timestamp
+            "01",               // priority
+            "0B4E1F80",         // longitude
+            "1A2B3C4D",         // latitude
+            "0064",             // altitude
+            "00B4",             // angle
+            "07",               // satellites
+            "0032",             // speed
+            "00F7",             // event id
+            "06",               // generation type
+            "04",               // IO count
+            "01",               // 1 byte IO count
+            "00EF",             // id
+            "01",               // value
+            "01",               // 2 bytes IO count
+            "0042",             // id
+            "2F3A",             // value
+            "01",               // 4 bytes IO count
+            "00F1",             // id
+            "000061A8",         // value
+            "01",               // 8 bytes IO count
+            "0010",             // id
+            "00000000075BCD15", // value
+            "01",               // number of records
+            "0000F2EA",         // CRC
+        ))
+        .unwrap();
+        let (rest, frame) = tcp_frame(&input).unwrap();
+        assert!(rest.is_empty());
+
+        let frame = frame.unwrap_avl();
+        assert_eq!(frame.codec, Codec::C16);
+        let record = &frame.records[0];
+        assert_eq!(
+            record.timestamp,
+            "2019-06-10T10:05:36Z"
+                .parse::<chrono::DateTime<chrono::Utc>>()
+                .unwrap()
+        );
+        assert_eq!(record.priority, Priority::High);
+        assert_eq!(record.longitude_raw, 189669248);
+        assert_eq!(record.latitude_raw, 439041101);
+        assert_eq!(record.altitude, 100);
+        assert_eq!(record.angle, 180);
+        assert_eq!(record.satellites, 7);
+        assert_eq!(record.speed, 50);
+        assert_eq!(record.trigger_event_id, 247);
+        assert_eq!(record.generation_type, Some(EventGenerationCause::Eventual));
+        assert_eq!(
+            record.io_events,
+            vec![
+                AVLEventIO {
+                    id: 239,
+                    value: AVLEventIOValue::U8(1)
+                },
+                AVLEventIO {
+                    id: 66,
+                    value: AVLEventIOValue::U16(12090)
+                },
+                AVLEventIO {
+                    id: 241,
+                    value: AVLEventIOValue::U32(25000)
+                },
+                AVLEventIO {
+                    id: 16,
+                    value: AVLEventIOValue::U64(123456789)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_frame_padding_io() {
+        // Codec 8 Extended record with a padding IO event (id 0) before the ignition (id 239)
+        let input = hex::decode(PADDING_IO_FRAME).unwrap();
+        let ignition = AVLEventIO {
+            id: 239,
+            value: AVLEventIOValue::U8(1),
+        };
+
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let record = &frame.unwrap_avl().records[0];
+        assert_eq!(
+            record.io_events,
+            vec![
+                AVLEventIO {
+                    id: 0,
+                    value: AVLEventIOValue::U8(0),
+                },
+                ignition.clone(),
+            ]
+        );
+        assert_eq!(
+            record.io_events_without_padding().collect::<Vec<_>>(),
+            vec![&ignition]
+        );
+
+        let options = ParseOptions {
+            drop_padding_io: true,
+            ..Default::default()
+        };
+        let (_, frame) = tcp_frame_with_options(options)(&input).unwrap();
+        assert_eq!(frame.unwrap_avl().records[0].io_events, vec![ignition]);
+    }
+
+    #[test]
+    fn parse_frame_swapped_speed_angle() {
+        // Speed of 50 km/h sent where the angle goes, angle of 180° where the speed goes
+        let input = hex::decode(SWAPPED_SPEED_ANGLE_FRAME).unwrap();
+
+        let (_, frame) = tcp_frame(&input).unwrap();
+        let record = &frame.unwrap_avl().records[0];
+        assert_eq!((record.speed, record.angle), (180, 50));
+
+        let options = ParseOptions {
+            quirk_swap_speed_angle: true,
+            ..Default::default()
+        };
+        let (_, frame) = tcp_frame_with_options(options)(&input).unwrap();
+        let record = &frame.unwrap_avl().records[0];
+        assert_eq!((record.speed, record.angle), (50, 180));
+        assert_eq!(record.satellites, 7);
+    }
+
+    #[test]
+    fn parse_record_timestamp_out_of_range() {
+        let input = hex::decode("7FFFFFFFFFFFFFFF010000000000000000000000000000000105021503010101425E0F01F10000601A014E0000000000000000").unwrap();
+        let err = record(Codec::C8, &ParseOptions::default())(&input).unwrap_err();
+        assert_eq!(
+            err,
+            nom::Err::Failure(nom::error::Error::new(
+                &input[..],
+                nom::error::ErrorKind::MapOpt
+            ))
+        );
+
+        // In range as UTC, out of range once converted from local time
+        let mut input = input;
+        input[..8].copy_from_slice(
+            &chrono::DateTime::<Utc>::MIN_UTC
+                .timestamp_millis()
+                .to_be_bytes(),
+        );
+        assert!(record(Codec::C8, &ParseOptions::default())(&input).is_ok());
+        let options = ParseOptions {
+            timestamp_is_local: Some(FixedOffset::east_opt(2 * 3600).unwrap()),
+            ..Default::default()
+        };
+        let err = record(Codec::C8, &options)(&input).unwrap_err();
+        assert!(matches!(err, nom::Err::Failure(_)));
+    }
+
+    #[test]
+    fn parse_udp_datagram_local_timestamps() {
+        let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+        let options = ParseOptions {
+            timestamp_is_local: Some(FixedOffset::east_opt(2 * 3600).unwrap()),
+            ..Default::default()
+        };
+        let (_, datagram) = udp_datagram_with_options(options)(&input).unwrap();
+        assert_eq!(
+            datagram.records[0].timestamp,
+            "2019-06-13T04:23:26Z"
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_frame_local_timestamps() {
+        // Sent at 10:05:36 local time
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+
+        let options = ParseOptions {
+            timestamp_is_local: Some(FixedOffset::east_opt(2 * 3600).unwrap()),
+            ..Default::default()
+        };
+        let (_, frame) = tcp_frame_with_options(options)(&input).unwrap();
+        assert_eq!(
+            frame.unwrap_avl().records[0].timestamp,
+            Utc.with_ymd_and_hms(2019, 6, 10, 8, 5, 36).unwrap()
+        );
+
+        let (_, frame) = tcp_frame(&input).unwrap();
+        assert_eq!(
+            frame.unwrap_avl().records[0].timestamp,
+            Utc.with_ymd_and_hms(2019, 6, 10, 10, 5, 36).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_single_io_group() {
+        // u16 group of the record in parse_frame_codec8ext, without its count
+        let input = hex::decode("00110018001D0001000E000000001DD7E06A").unwrap();
+        let (rest, events) = parse_io_group(Codec::C8Ext, 2, 2, &input).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                AVLEventIO {
+                    id: 0x11,
+                    value: AVLEventIOValue::U16(0x18)
+                },
+                AVLEventIO {
+                    id: 0x1D,
+                    value: AVLEventIOValue::U16(0x01)
+                },
+            ]
+        );
+        assert_eq!(rest, &input[8..]);
+
+        assert!(matches!(
+            parse_io_group(Codec::C8Ext, 2, 3, &input[..8]),
+            Err(nom::Err::Incomplete(_))
+        ));
+        assert!(matches!(
+            parse_io_group(Codec::C8Ext, 3, 1, &input),
+            Err(nom::Err::Error(_))
+        ));
+        assert!(matches!(
+            parse_io_group(Codec::C12, 2, 1, &input),
+            Err(nom::Err::Error(_))
+        ));
+    }
+
+    #[test]
+    fn parse_record_excessive_group_counts() {
+        let mut input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        // The record declares 5 IO events, but its u8 group 65535
+        assert_eq!(input[36..40], [0x00, 0x05, 0x00, 0x01]);
+        input[38..40].copy_from_slice(&[0xFF, 0xFF]);
+
+        let options = ParseOptions {
+            max_io_per_record: Some(10),
+            ..Default::default()
+        };
+        match tcp_frame_with_options(options)(&input) {
+            Err(nom::Err::Error(e)) => {
+                assert_eq!(e.code, nom::error::ErrorKind::TooLarge);
+                assert_eq!(e.input.len(), input.len() - 4 - 38);
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_frame_u16_data_length() {
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+        // Same frame with its data length re-framed on 2 bytes
+        let reframed = hex::decode("00000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+        let options = ParseOptions {
+            u16_data_length: true,
+            ..Default::default()
+        };
+
+        let (rest, frame) = tcp_frame_with_options(&options)(&reframed).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(frame, tcp_frame(&input).unwrap().1);
+
+        let mut corrupted = reframed.clone();
+        let length = corrupted.len();
+        corrupted[length - 1] ^= 0xFF;
+        match tcp_frame_complete_with_options(&corrupted, &options) {
+            Err(TeltonikaParseError::CrcMismatch { calculated, .. }) => {
+                assert_eq!(calculated, 0xF22A)
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+        assert_eq!(
+            tcp_frame_complete_with_options(&reframed[..20], &options),
+            Err(TeltonikaParseError::Truncated {
+                declared: reframed.len(),
+                available: 20
+            })
+        );
+    }
+
+    #[test]
+    fn frame_crc_candidates() {
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+        let (rest, candidates) = crc_candidates(&input).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            candidates
+                .iter()
+                .map(|(range, _)| *range)
+                .collect::<Vec<_>>(),
+            CrcRange::ALL
+        );
+        assert_eq!(candidates[0], (CrcRange::Data, 0xF22A));
+        assert_eq!(
+            candidates.iter().filter(|(_, crc)| *crc == 0xF22A).count(),
+            1
+        );
+        assert_eq!(
+            candidates[4],
+            (CrcRange::Frame, crate::crc16(&input[..input.len() - 4]))
+        );
+    }
+
+    #[test]
+    fn check_frame_crc() {
+        let input = hex::decode(CODEC8_FRAME_2).unwrap();
+        assert_eq!(check_crc(&input), Ok((&[][..], true)));
+
+        let mut corrupted = input.clone();
+        corrupted[20] ^= 0xFF;
+        assert_eq!(check_crc(&corrupted), Ok((&[][..], false)));
+
+        assert!(matches!(
+            check_crc(&input[..input.len() - 1]),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn parse_trusted_frame() {
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        assert_eq!(check_crc(&input), Ok((&[][..], true)));
+        assert_eq!(tcp_frame_trusted(&input), tcp_frame(&input));
+
+        // The CRC is not computed, a wrong one goes unnoticed
+        let mut corrupted = input.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xFF;
+        assert!(tcp_frame(&corrupted).is_err());
+        assert!(tcp_frame_trusted(&corrupted).is_ok());
+    }
+
+    #[test]
+    fn diagnose_frames() {
+        let input = hex::decode(CODEC8_FRAME_3).unwrap();
+        assert_eq!(
+            diagnose(&input),
+            Ok((
+                &[][..],
+                FrameDiagnostics {
+                    declared_data_len: 0x43,
+                    actual_data_len: 0x43,
+                    declared_records: 2,
+                    actual_records: 2,
+                    crc_ok: true,
+                }
+            ))
+        );
+
+        // First count claiming 3 records
+        let mut mismatched = input.clone();
+        mismatched[9] = 3;
+        assert!(tcp_frame(&mismatched).is_err());
+        assert_eq!(
+            diagnose(&mismatched).unwrap().1,
+            FrameDiagnostics {
+                declared_data_len: 0x43,
+                actual_data_len: 0x43,
+                declared_records: 3,
+                actual_records: 2,
+                crc_ok: false,
+            }
+        );
+
+        // Last record cut short by the data length
+        let mut truncated = input[..input.len() - 10].to_vec();
+        truncated[7] -= 6;
+        truncated.extend([0x00, 0x00, 0x00, 0x00]);
+        let (_, diagnostics) = diagnose(&truncated).unwrap();
+        assert_eq!(diagnostics.declared_records, 2);
+        assert_eq!(diagnostics.actual_records, 1);
+        // Codec, first count and the first record, the second one is left unread
+        assert_eq!(diagnostics.actual_data_len, 0x22);
+
+        let input = hex::decode(CODEC8EXT_FRAME).unwrap();
+        let (_, diagnostics) = diagnose(&input).unwrap();
+        assert_eq!(diagnostics.actual_records, 1);
+        assert_eq!(diagnostics.actual_data_len, 0x4A);
+        assert!(diagnostics.crc_ok);
+    }
 }
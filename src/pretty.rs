@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use crate::{AVLRecord, Codec, HasRecords, IoIdMap, TeltonikaFrame};
+
+/// Human readable name of a codec
+fn codec_name(codec: Codec) -> &'static str {
+    match codec {
+        Codec::C8 => "Codec 8",
+        Codec::C8Ext => "Codec 8 Extended",
+        Codec::C16 => "Codec 16",
+        Codec::C12 => "Codec 12",
+        Codec::C13 => "Codec 13",
+        Codec::C14 => "Codec 14",
+    }
+}
+
+fn write_record(out: &mut String, index: usize, record: &AVLRecord, map: Option<&IoIdMap>) {
+    let _ = writeln!(out, "Record {index}");
+    let _ = writeln!(out, "  Time: {}", record.timestamp.to_rfc3339());
+    let _ = writeln!(out, "  Priority: {:?}", record.priority);
+    let _ = writeln!(
+        out,
+        "  Position: {:.6}, {:.6}",
+        record.latitude, record.longitude
+    );
+    let _ = writeln!(
+        out,
+        "  Altitude: {} m, Angle: {}°, Satellites: {}, Speed: {} km/h",
+        record.altitude_signed(),
+        record.angle,
+        record.satellites,
+        record.speed
+    );
+    match record.generation_type {
+        Some(generation_type) => {
+            let _ = writeln!(
+                out,
+                "  Event: {} ({generation_type:?})",
+                record.trigger_event_id
+            );
+        }
+        None => {
+            let _ = writeln!(out, "  Event: {}", record.trigger_event_id);
+        }
+    }
+    let _ = writeln!(out, "  IO events: {}", record.io_events.len());
+    for io in &record.io_events {
+        match map.and_then(|map| map.describe(io)) {
+            Some(description) => {
+                let _ = write!(
+                    out,
+                    "    {} ({}): {}",
+                    description.name(),
+                    io.id,
+                    description.value
+                );
+                match description.units() {
+                    Some(units) => {
+                        let _ = writeln!(out, " {units}");
+                    }
+                    None => out.push('\n'),
+                }
+            }
+            None => {
+                let _ = writeln!(out, "    {}: {}", io.id, io.value);
+            }
+        }
+    }
+}
+
+impl TeltonikaFrame {
+    /// Renders the frame as a multi-line report, e.g. for command line tools.
+    ///
+    /// Timestamps are written as RFC 3339 and coordinates with 6 decimals (about 10 cm),
+    /// IO events found in `map` are written with their name, scaled value and units,
+    /// the other ones with their id and raw value.
+    pub fn to_pretty_string(&self, map: Option<&IoIdMap>) -> String {
+        let mut out = String::new();
+        match self {
+            Self::AVL(frame) => {
+                let _ = writeln!(
+                    out,
+                    "AVL frame, {}, {} records, CRC {:#06X}",
+                    codec_name(frame.codec),
+                    frame.total_records(),
+                    frame.crc16
+                );
+                for (index, record) in frame.records.iter().enumerate() {
+                    write_record(&mut out, index + 1, record, map);
+                }
+            }
+            Self::GPRS(frame) => {
+                let _ = writeln!(
+                    out,
+                    "GPRS frame, {}, {:?}, {} messages, CRC {:#06X}",
+                    codec_name(frame.codec),
+                    frame.command_type,
+                    frame.command_responses.len(),
+                    frame.crc16
+                );
+                if let Some(imei) = &frame.imei {
+                    let _ = writeln!(out, "  IMEI: {imei}");
+                }
+                for response in &frame.command_responses {
+                    let _ = writeln!(out, "  {:?}", response.as_str_lossy());
+                }
+            }
+            Self::KeepAlive => out.push_str("Keep-alive\n"),
+        }
+        out
+    }
+}
@@ -3,7 +3,10 @@ use chrono::{DateTime, Utc};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use crate::parser::{tcp_frame, udp_datagram};
+use crate::{
+    parser::{tcp_frame, tcp_frame_complete, udp_datagram, udp_datagram_complete},
+    FromHexError, Imei, TeltonikaParseError,
+};
 
 /// Represent the device Codec
 ///
@@ -12,8 +15,9 @@ use crate::parser::{tcp_frame, udp_datagram};
 /// | C8      | C12  |
 /// | C8Ext   | C13  |
 /// | C16     | C14  |
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Codec {
     C8,
     C8Ext,
@@ -23,16 +27,39 @@ pub enum Codec {
     C14,
 }
 
-impl From<u8> for Codec {
-    fn from(value: u8) -> Self {
+impl Codec {
+    /// Every codec, AVL ones first
+    pub fn all() -> &'static [Codec] {
+        &[
+            Self::C8,
+            Self::C8Ext,
+            Self::C16,
+            Self::C12,
+            Self::C13,
+            Self::C14,
+        ]
+    }
+
+    /// Width in bytes of the record count before and after the records, or of the command count of GPRS frames
+    ///
+    /// It is 1 byte for every codec, even C8Ext having 2 byte IO counts, so a frame holds at most 255 records.
+    pub fn record_count_width(&self) -> usize {
+        std::mem::size_of::<u8>()
+    }
+}
+
+impl TryFrom<u8> for Codec {
+    type Error = UnknownCodec;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            0x08 => Self::C8,
-            0x8E => Self::C8Ext,
-            0x10 => Self::C16,
-            0x0C => Self::C12,
-            0x0D => Self::C13,
-            0x0E => Self::C14,
-            _ => panic!("Unknown value: {}", value),
+            0x08 => Ok(Self::C8),
+            0x8E => Ok(Self::C8Ext),
+            0x10 => Ok(Self::C16),
+            0x0C => Ok(Self::C12),
+            0x0D => Ok(Self::C13),
+            0x0E => Ok(Self::C14),
+            _ => Err(UnknownCodec(value)),
         }
     }
 }
@@ -50,33 +77,168 @@ impl From<Codec> for u8 {
     }
 }
 
+/// Byte that is not a valid [`Codec`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UnknownCodec(pub u8);
+
+impl std::fmt::Display for UnknownCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown codec: {:#04X}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCodec {}
+
+/// Type of a Codec12 message, telling its direction
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Codec12Type {
+    /// Command sent by the server to the device
+    Command,
+    /// Response sent by the device to the server
+    Response,
+    /// Codec 14 response of a device whose IMEI does not match the one of the command
+    Nack,
+}
+
+impl Codec12Type {
+    /// Every Codec12 message type
+    pub fn all() -> &'static [Codec12Type] {
+        &[Self::Command, Self::Response, Self::Nack]
+    }
+}
+
+impl TryFrom<u8> for Codec12Type {
+    type Error = UnknownCodec12Type;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x05 => Ok(Self::Command),
+            0x06 => Ok(Self::Response),
+            0x11 => Ok(Self::Nack),
+            _ => Err(UnknownCodec12Type(value)),
+        }
+    }
+}
+
+impl From<Codec12Type> for u8 {
+    fn from(value: Codec12Type) -> u8 {
+        match value {
+            Codec12Type::Command => 0x05,
+            Codec12Type::Response => 0x06,
+            Codec12Type::Nack => 0x11,
+        }
+    }
+}
+
+/// GPRS codec used to send commands to a device
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CommandCodec {
+    /// Plain commands
+    C12,
+    /// Commands prefixed by a timestamp, sent as seconds since the unix epoch
+    C13 { timestamp: DateTime<Utc> },
+    /// Commands addressed to a specific device,
+    /// which only executes them if the IMEI matches its own
+    C14 { imei: Imei },
+}
+
+impl CommandCodec {
+    /// The [`Codec`] written in the frame
+    pub fn codec(&self) -> Codec {
+        match self {
+            Self::C12 => Codec::C12,
+            Self::C13 { .. } => Codec::C13,
+            Self::C14 { .. } => Codec::C14,
+        }
+    }
+}
+
+/// Byte that is not a valid [`Codec12Type`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UnknownCodec12Type(pub u8);
+
+impl std::fmt::Display for UnknownCodec12Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown Codec12 type: {:#04X}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCodec12Type {}
+
+/// Byte that is not a valid [`Priority`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UnknownPriority(pub u8);
+
+impl std::fmt::Display for UnknownPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown priority: {:#04X}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPriority {}
+
 /// Record priority
 ///
 /// Indicates based on configuration how important the record is
 #[derive(Debug, PartialEq, Copy, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Priority {
     Low,
     High,
     Panic,
 }
 
-impl From<u8> for Priority {
-    fn from(value: u8) -> Self {
+impl Priority {
+    /// Every priority, from the lowest
+    pub fn all() -> &'static [Priority] {
+        &[Self::Low, Self::High, Self::Panic]
+    }
+}
+
+impl TryFrom<u8> for Priority {
+    type Error = UnknownPriority;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            0x00 => Self::Low,
-            0x01 => Self::High,
-            0x02 => Self::Panic,
-            _ => panic!("Unknown value: {}", value),
+            0x00 => Ok(Self::Low),
+            0x01 => Ok(Self::High),
+            0x02 => Ok(Self::Panic),
+            _ => Err(UnknownPriority(value)),
         }
     }
 }
 
+impl From<Priority> for u8 {
+    fn from(value: Priority) -> u8 {
+        match value {
+            Priority::Low => 0x00,
+            Priority::High => 0x01,
+            Priority::Panic => 0x02,
+        }
+    }
+}
+
+/// Byte that is not a valid [`EventGenerationCause`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct UnknownEventGenerationCause(pub u8);
+
+impl std::fmt::Display for UnknownEventGenerationCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown event generation cause: {:#04X}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownEventGenerationCause {}
+
 /// Event generation
 ///
 /// Indicates the cause for the event trigger see [`AVLRecord`]
 #[derive(Debug, PartialEq, Copy, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum EventGenerationCause {
     None,
     OnExit,
@@ -89,18 +251,54 @@ pub enum EventGenerationCause {
     Periodical,
 }
 
-impl From<u8> for EventGenerationCause {
-    fn from(value: u8) -> Self {
+impl EventGenerationCause {
+    /// Every cause, [`EventGenerationCause::None`] first although it has no wire value
+    pub fn all() -> &'static [EventGenerationCause] {
+        &[
+            Self::None,
+            Self::OnExit,
+            Self::OnEntrance,
+            Self::OnBoth,
+            Self::Reserved,
+            Self::Hysteresis,
+            Self::OnChange,
+            Self::Eventual,
+            Self::Periodical,
+        ]
+    }
+}
+
+impl TryFrom<u8> for EventGenerationCause {
+    type Error = UnknownEventGenerationCause;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            0 => Self::OnExit,
-            1 => Self::OnEntrance,
-            2 => Self::OnBoth,
-            3 => Self::Reserved,
-            4 => Self::Hysteresis,
-            5 => Self::OnChange,
-            6 => Self::Eventual,
-            7 => Self::Periodical,
-            _ => panic!("Unknown value: {}", value),
+            0 => Ok(Self::OnExit),
+            1 => Ok(Self::OnEntrance),
+            2 => Ok(Self::OnBoth),
+            3 => Ok(Self::Reserved),
+            4 => Ok(Self::Hysteresis),
+            5 => Ok(Self::OnChange),
+            6 => Ok(Self::Eventual),
+            7 => Ok(Self::Periodical),
+            _ => Err(UnknownEventGenerationCause(value)),
+        }
+    }
+}
+
+impl From<EventGenerationCause> for u8 {
+    /// [`EventGenerationCause::None`] has no wire value and is written as `0xFF`
+    fn from(value: EventGenerationCause) -> u8 {
+        match value {
+            EventGenerationCause::OnExit => 0,
+            EventGenerationCause::OnEntrance => 1,
+            EventGenerationCause::OnBoth => 2,
+            EventGenerationCause::Reserved => 3,
+            EventGenerationCause::Hysteresis => 4,
+            EventGenerationCause::OnChange => 5,
+            EventGenerationCause::Eventual => 6,
+            EventGenerationCause::Periodical => 7,
+            EventGenerationCause::None => 0xFF,
         }
     }
 }
@@ -115,12 +313,41 @@ pub struct AVLDatagram {
     pub packet_id: u16,
     /// The actual id of the AVL packet
     pub avl_packet_id: u8,
-    pub imei: String,
+    pub imei: Imei,
     pub codec: Codec,
     /// All the records sent with this datagram
     pub records: Vec<AVLRecord>,
 }
 
+impl AVLDatagram {
+    /// Parses a complete UDP datagram, see [`crate::parser::udp_datagram_complete`]
+    ///
+    /// Bytes following the datagram are ignored.
+    ///
+    /// ```rust
+    /// use nom_teltonika::AVLDatagram;
+    ///
+    /// let input = [
+    ///     0x00, 0x3D, 0xCA, 0xFE, 0x01, 0x05, 0x00, 0x0F, 0x33, 0x35, 0x32, 0x30, 0x39, 0x33, 0x30,
+    ///     0x38, 0x36, 0x34, 0x30, 0x33, 0x36, 0x35, 0x35, 0x08, 0x01, 0x00, 0x00, 0x01, 0x6B, 0x4F,
+    ///     0x81, 0x5B, 0x30, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ///     0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x02, 0x15, 0x03, 0x01, 0x01, 0x01, 0x42, 0x5D, 0xBC,
+    ///     0x00, 0x00, 0x01,
+    /// ];
+    /// let datagram = AVLDatagram::parse(&input).unwrap();
+    /// assert_eq!(datagram.imei.as_str(), "352093086403655");
+    /// assert!(AVLDatagram::parse(&input[..20]).is_err());
+    /// ```
+    pub fn parse(input: &[u8]) -> Result<Self, TeltonikaParseError> {
+        udp_datagram_complete(input).map(|(_, datagram)| datagram)
+    }
+
+    /// Same as [`AVLFrame::payload_eq`], datagrams do not carry a CRC so it is the same as `==`
+    pub fn payload_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
 impl<'a> TryFrom<&'a [u8]> for AVLDatagram {
     type Error = nom::Err<nom::error::Error<&'a [u8]>>;
 
@@ -132,6 +359,45 @@ impl<'a> TryFrom<&'a [u8]> for AVLDatagram {
     }
 }
 
+/// Beginning of a TCP frame, enough to route it without parsing its records
+///
+/// See [`crate::parser::frame_header`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FrameHeader {
+    /// Length of the data field, from the codec to the second count included
+    pub data_length: u32,
+    pub codec: Codec,
+    /// Number of records, or of commands for GPRS frames
+    pub record_count: usize,
+}
+
+impl FrameHeader {
+    /// Length of the whole frame: preamble, data length, data and CRC
+    pub fn frame_length(&self) -> usize {
+        4 + 4 + self.data_length as usize + 4
+    }
+}
+
+/// Integrity summary of a TCP frame, comparing its header with what it holds
+///
+/// See [`crate::parser::diagnose`]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FrameDiagnostics {
+    /// Length of the data field declared by the header
+    pub declared_data_len: usize,
+    /// Length of the data field read: codec, first count and records found,
+    /// plus the second count when it is all that follows them
+    pub actual_data_len: usize,
+    /// Number of records declared before the records
+    pub declared_records: usize,
+    /// Number of records found in the data field
+    pub actual_records: usize,
+    /// Whether the CRC sent matches the one calculated on the data field
+    pub crc_ok: bool,
+}
+
 /// Frame sent by the device when sending records
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -140,34 +406,212 @@ pub struct AVLFrame {
     /// All the records sent with this frame
     pub records: Vec<AVLRecord>,
     /// CRC16 Calculated using [IBM/CRC16][super::crc16] algorithm and 0xA001 polynomial
+    ///
+    /// As received, it goes stale once the records change: [`AVLFrame::to_bytes`] computes a fresh one.
     pub crc16: u32,
 }
 
-impl<'a> TryFrom<&'a [u8]> for AVLFrame {
-    type Error = nom::Err<nom::error::Error<&'a [u8]>>;
+impl AVLFrame {
+    /// Appends a record, [`AVLFrame::crc16`] is not updated
+    pub fn push_record(&mut self, record: AVLRecord) {
+        self.records.push(record);
+    }
 
-    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
-        match tcp_frame(value) {
-            Ok((_, TeltonikaFrame::AVL(frame))) => Ok(frame),
+    /// Iterates over the records to change them in place, e.g. to enrich them before forwarding the frame
+    ///
+    /// [`AVLFrame::crc16`] is not updated: encode the frame with [`AVLFrame::to_bytes`],
+    /// which computes the CRC of the changed records.
+    pub fn records_iter_mut(&mut self) -> std::slice::IterMut<'_, AVLRecord> {
+        self.records.iter_mut()
+    }
+
+    /// Parses a complete AVL frame, see [`crate::parser::tcp_frame_complete`]
+    ///
+    /// GPRS frames and keep-alives fail with [`TeltonikaParseError::NotAvl`],
+    /// see [`TeltonikaFrame::parse`] to accept them. Bytes following the frame are ignored.
+    ///
+    /// ```rust
+    /// use nom_teltonika::AVLFrame;
+    ///
+    /// let input = [
+    ///     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x28, 0x08, 0x01, 0x00, 0x00, 0x01, 0x6B, 0x40,
+    ///     0xD9, 0xAD, 0x80, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ///     0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x02, 0x15, 0x03, 0x01, 0x01, 0x01, 0x42, 0x5E, 0x10,
+    ///     0x00, 0x00, 0x01, 0x00, 0x00, 0xF2, 0x2A,
+    /// ];
+    /// let frame = AVLFrame::parse(&input).unwrap();
+    /// assert_eq!(frame.records.len(), 1);
+    /// ```
+    pub fn parse(input: &[u8]) -> Result<Self, TeltonikaParseError> {
+        match TeltonikaFrame::parse(input)? {
+            TeltonikaFrame::AVL(frame) => Ok(frame),
+            _ => Err(TeltonikaParseError::NotAvl),
+        }
+    }
+
+    /// Compares the codec and the records, ignoring the stored CRC
+    pub fn payload_eq(&self, other: &Self) -> bool {
+        self.codec == other.codec && self.records == other.records
+    }
+
+    /// Parses an AVL frame, returning the bytes following it along with it
+    ///
+    /// GPRS frames and keep-alives fail with [`nom::error::ErrorKind::Fail`]
+    #[allow(clippy::type_complexity)]
+    pub fn parse_with_remainder(
+        input: &[u8],
+    ) -> Result<(AVLFrame, &[u8]), nom::Err<nom::error::Error<&[u8]>>> {
+        match tcp_frame(input) {
+            Ok((rest, TeltonikaFrame::AVL(frame))) => Ok((frame, rest)),
             Err(e) => Err(e),
             _ => Err(nom::Err::Failure(nom::error::Error::new(
-                value,
+                input,
                 nom::error::ErrorKind::Fail,
             ))),
         }
     }
+
+    /// Parses an AVL frame from its hex representation, e.g. as captured while debugging
+    ///
+    /// Whitespace is ignored and so are the bytes following the frame.
+    ///
+    /// ```rust
+    /// use nom_teltonika::AVLFrame;
+    ///
+    /// let frame = AVLFrame::from_hex(
+    ///     "00000000 00000028 08 01 0000016B40D9AD80 01 00000000 00000000 0000 0000 00 0000
+    ///     01 03 02 15 03 01 01 01 42 5E10 00 00 01 0000F22A",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(frame.records.len(), 1);
+    /// ```
+    pub fn from_hex(s: &str) -> Result<AVLFrame, FromHexError> {
+        let digits = s
+            .char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .map(|(index, c)| {
+                c.to_digit(16)
+                    .map(|digit| digit as u8)
+                    .ok_or(FromHexError::InvalidCharacter { c, index })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if digits.len() % 2 != 0 {
+            return Err(FromHexError::OddLength);
+        }
+        let bytes: Vec<u8> = digits
+            .chunks_exact(2)
+            .map(|pair| pair[0] << 4 | pair[1])
+            .collect();
+        Self::parse(&bytes).map_err(FromHexError::Parse)
+    }
+}
+
+/// Appends records, [`AVLFrame::crc16`] is not updated
+impl Extend<AVLRecord> for AVLFrame {
+    fn extend<T: IntoIterator<Item = AVLRecord>>(&mut self, iter: T) {
+        self.records.extend(iter);
+    }
+}
+
+/// Same as [`AVLFrame::parse`], see [`AVLFrame::parse_with_remainder`] to get the bytes following the frame
+impl TryFrom<&[u8]> for AVLFrame {
+    type Error = TeltonikaParseError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse(value)
+    }
+}
+
+/// Common view over the containers of [`AVLRecord`]s, whatever their envelope
+///
+/// Implemented by both [`AVLFrame`] (TCP) and [`AVLDatagram`] (UDP)
+pub trait HasRecords {
+    /// Codec the records were encoded with
+    fn codec(&self) -> Codec;
+    fn records(&self) -> &[AVLRecord];
+
+    fn total_records(&self) -> usize {
+        self.records().len()
+    }
+
+    /// Number of IO events summed across all the records
+    fn total_io_events(&self) -> usize {
+        self.records()
+            .iter()
+            .map(|record| record.io_events.len())
+            .sum()
+    }
+
+    /// Records taken from `start` included to `end` excluded, in the order they were sent
+    fn records_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> RecordsBetween<'_> {
+        RecordsBetween {
+            records: self.records().iter(),
+            range: start..end,
+        }
+    }
+}
+
+/// Iterator over the records sent in a time range, see [`HasRecords::records_between`]
+#[derive(Debug, Clone)]
+pub struct RecordsBetween<'a> {
+    records: std::slice::Iter<'a, AVLRecord>,
+    range: std::ops::Range<DateTime<Utc>>,
+}
+
+impl<'a> Iterator for RecordsBetween<'a> {
+    type Item = &'a AVLRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = &self.range;
+        self.records
+            .find(|record| range.contains(&record.timestamp))
+    }
+}
+
+impl HasRecords for AVLFrame {
+    fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    fn records(&self) -> &[AVLRecord] {
+        &self.records
+    }
+}
+
+impl HasRecords for AVLDatagram {
+    fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    fn records(&self) -> &[AVLRecord] {
+        &self.records
+    }
 }
 
 /// Location and IO Status information at a certain point in time
+///
+/// The raw coordinates are the ones encoded, the degrees must match them,
+/// see [`AVLRecord::set_coordinates`] to change both.
+/// When deserializing, missing raw coordinates are derived from the degrees.
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "AVLRecordRepr"))]
 pub struct AVLRecord {
     /// In Utc Dates
     pub timestamp: DateTime<Utc>,
     /// How important this record is, see [`Priority`]
     pub priority: Priority,
+    /// Degrees, derived from [`AVLRecord::longitude_raw`]
     pub longitude: f64,
+    /// Degrees, derived from [`AVLRecord::latitude_raw`]
     pub latitude: f64,
+    /// Longitude as sent by the device, degrees multiplied by 10^7
+    pub longitude_raw: i32,
+    /// Latitude as sent by the device, degrees multiplied by 10^7
+    pub latitude_raw: i32,
+    /// Meters above sea level, unsigned as per the base specification.
+    ///
+    /// Some devices report altitudes below sea level as a signed value, see [`AVLRecord::altitude_signed`]
     pub altitude: u16,
     /// Degrees
     pub angle: u16,
@@ -183,9 +627,173 @@ pub struct AVLRecord {
     pub io_events: Vec<AVLEventIO>,
 }
 
-/// IO event status
+impl AVLRecord {
+    /// Sets the coordinates in degrees along with their raw values,
+    /// rounded to the 10^-7 degrees sent by devices
+    pub fn set_coordinates(&mut self, longitude: f64, latitude: f64) {
+        self.longitude_raw = (longitude * 10000000.0).round() as i32;
+        self.latitude_raw = (latitude * 10000000.0).round() as i32;
+        self.longitude = self.longitude_raw as f64 / 10000000.0;
+        self.latitude = self.latitude_raw as f64 / 10000000.0;
+    }
+
+    /// Whether the degrees match the raw coordinates, as required to encode the record
+    pub fn coordinates_match(&self) -> bool {
+        self.longitude == self.longitude_raw as f64 / 10000000.0
+            && self.latitude == self.latitude_raw as f64 / 10000000.0
+    }
+
+    /// Altitude read as a signed 16 bit value, for devices reporting altitudes below sea level.
+    ///
+    /// With those devices `-30` meters is parsed as an [`AVLRecord::altitude`] of `65506`.
+    pub fn altitude_signed(&self) -> i16 {
+        self.altitude as i16
+    }
+
+    /// Orders records by their timestamp, e.g. `records.sort_by(AVLRecord::cmp_by_time)`
+    pub fn cmp_by_time(&self, other: &Self) -> std::cmp::Ordering {
+        self.timestamp.cmp(&other.timestamp)
+    }
+
+    /// Whether the record was triggered by an event but carries no IO values,
+    /// as sent by firmwares reporting the event only through [`AVLRecord::trigger_event_id`]
+    pub fn is_bare_event(&self) -> bool {
+        self.trigger_event_id != 0 && self.io_events.is_empty()
+    }
+
+    /// IO events except the ones with id 0
+    ///
+    /// No IO element is assigned id 0 in the Teltonika AVL ID lists,
+    /// some devices send it as padding, mostly in Codec 8 Extended frames.
+    /// See [`ParseOptions::drop_padding_io`](crate::parser::ParseOptions::drop_padding_io) to drop them while parsing.
+    pub fn io_events_without_padding(&self) -> impl Iterator<Item = &AVLEventIO> {
+        self.io_events.iter().filter(|io| io.id != 0)
+    }
+
+    /// Great-circle distance in meters to `other`, using the haversine formula on a sphere
+    /// of [`EARTH_RADIUS_METERS`]
+    ///
+    /// `None` when either record has no GPS fix (no satellites in use), as its position is meaningless.
+    pub fn distance_to(&self, other: &AVLRecord) -> Option<f64> {
+        if self.satellites == 0 || other.satellites == 0 {
+            return None;
+        }
+        let (latitude, other_latitude) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let half_latitude = (other_latitude - latitude) / 2.0;
+        let half_longitude = (other.longitude - self.longitude).to_radians() / 2.0;
+
+        let a = half_latitude.sin().powi(2)
+            + latitude.cos() * other_latitude.cos() * half_longitude.sin().powi(2);
+        Some(2.0 * EARTH_RADIUS_METERS * a.sqrt().asin())
+    }
+
+    /// IO values by id, keeping a single value for ids sent more than once
+    ///
+    /// The widest value wins, variable length ones being the widest,
+    /// between values of the same width the last one wins.
+    pub fn merge_io(&self) -> std::collections::BTreeMap<u16, AVLEventIOValue> {
+        fn width(value: &AVLEventIOValue) -> u8 {
+            match value {
+                AVLEventIOValue::U8(_) => 1,
+                AVLEventIOValue::U16(_) => 2,
+                AVLEventIOValue::U32(_) => 4,
+                AVLEventIOValue::U64(_) => 8,
+                AVLEventIOValue::Variable(_)
+                | AVLEventIOValue::Parsed { .. }
+                | AVLEventIOValue::ParseFailed { .. } => u8::MAX,
+            }
+        }
+
+        let mut merged = std::collections::BTreeMap::new();
+        for io in &self.io_events {
+            match merged.get(&io.id) {
+                Some(value) if width(value) > width(&io.value) => {}
+                _ => {
+                    merged.insert(io.id, io.value.clone());
+                }
+            }
+        }
+        merged
+    }
+}
+
+/// Mean radius of the Earth in meters, as defined by the IUGG, used by [`AVLRecord::distance_to`]
+pub const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// Sorts records by timestamp, records with the same timestamp keep their order
+pub fn sort_records(records: &mut [AVLRecord]) {
+    records.sort_by(AVLRecord::cmp_by_time);
+}
+
+/// Splits time ordered records into trips, starting a new one
+/// whenever the time between two consecutive records exceeds `max_gap`
+///
+/// ```rust
+/// use nom_teltonika::{trips, AVLRecord};
+///
+/// # fn example(records: Vec<AVLRecord>) {
+/// for trip in trips(records, chrono::Duration::minutes(5)) {
+///     println!("{} -> {}", trip[0].timestamp, trip[trip.len() - 1].timestamp);
+/// }
+/// # }
+/// ```
+pub fn trips(
+    records: impl IntoIterator<Item = AVLRecord>,
+    max_gap: chrono::Duration,
+) -> impl Iterator<Item = Vec<AVLRecord>> {
+    let mut records = records.into_iter().peekable();
+    std::iter::from_fn(move || {
+        let mut trip = vec![records.next()?];
+        let mut last = trip[0].timestamp;
+        while let Some(record) = records.next_if(|record| record.timestamp - last <= max_gap) {
+            last = record.timestamp;
+            trip.push(record);
+        }
+        Some(trip)
+    })
+}
+
+/// Record keeping its fields exactly as sent, IO events included
+///
+/// Unlike [`AVLRecord`] the IO events are kept in their width groups, in wire order,
+/// along with the declared total count, so that re-encoding gives back the same bytes.
+/// See [`crate::parser::record_raw`]
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AVLRecordRaw {
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub priority: Priority,
+    /// Degrees multiplied by 10^7
+    pub longitude: i32,
+    /// Degrees multiplied by 10^7
+    pub latitude: i32,
+    pub altitude: u16,
+    pub angle: u16,
+    pub satellites: u8,
+    pub speed: u16,
+    pub trigger_event_id: u16,
+    pub generation_type: Option<EventGenerationCause>,
+    /// Total number of IO events, as declared by the device
+    pub io_count: u16,
+    /// IO events with 1 byte values, as (id, value)
+    pub io_u8: Vec<(u16, u8)>,
+    /// IO events with 2 bytes values, as (id, value)
+    pub io_u16: Vec<(u16, u16)>,
+    /// IO events with 4 bytes values, as (id, value)
+    pub io_u32: Vec<(u16, u32)>,
+    /// IO events with 8 bytes values, as (id, value)
+    pub io_u64: Vec<(u16, u64)>,
+    /// IO events with variable length values, as (id, value), only present with [`Codec::C8Ext`]
+    pub io_variable: Option<Vec<(u16, Vec<u8>)>>,
+}
+
+/// IO event status
+///
+/// Defaults to id 0 with a [`AVLEventIOValue::U8`] value of 0
+#[derive(Debug, Default, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AVLEventIO {
     /// Event ID
     pub id: u16,
@@ -195,8 +803,18 @@ pub struct AVLEventIO {
     pub value: AVLEventIOValue,
 }
 
+impl AVLEventIO {
+    pub fn new(id: u16, value: AVLEventIOValue) -> Self {
+        Self { id, value }
+    }
+}
+
+/// Value of an IO event
+///
+/// New variants may be added, e.g. for new decoded forms of the variable length values.
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum AVLEventIOValue {
     U8(u8),
     U16(u16),
@@ -204,6 +822,310 @@ pub enum AVLEventIOValue {
     U64(u64),
     #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     Variable(Vec<u8>),
+    /// Variable length value decoded by a [`VariableParser`](crate::VariableParser)
+    Parsed {
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
+        raw: Vec<u8>,
+        value: crate::ParsedVariable,
+    },
+    /// Variable length value its [`VariableParser`](crate::VariableParser) failed on
+    ParseFailed {
+        #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
+        raw: Vec<u8>,
+        error: crate::VariableParseError,
+    },
+}
+
+impl Default for AVLEventIOValue {
+    /// The smallest value, `U8(0)`
+    fn default() -> Self {
+        Self::U8(0)
+    }
+}
+
+/// Numeric values are rendered in decimal, variable length ones as `0x` prefixed hex
+impl std::fmt::Display for AVLEventIOValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::U8(value) => value.fmt(f),
+            Self::U16(value) => value.fmt(f),
+            Self::U32(value) => value.fmt(f),
+            Self::U64(value) => value.fmt(f),
+            Self::Variable(bytes) => f.write_str(&crate::hex_string(bytes)),
+            Self::Parsed { value, .. } => write!(f, "{value:?}"),
+            Self::ParseFailed { raw, .. } => f.write_str(&crate::hex_string(raw)),
+        }
+    }
+}
+
+impl AVLEventIOValue {
+    /// Whether the value is variable length, either raw or parsed
+    pub fn is_variable(&self) -> bool {
+        matches!(
+            self,
+            Self::Variable(_) | Self::Parsed { .. } | Self::ParseFailed { .. }
+        )
+    }
+
+    /// Raw bytes of a variable length value split into chunks of `N` bytes
+    fn chunked<const N: usize>(&self) -> Option<impl Iterator<Item = [u8; N]> + '_> {
+        let (Self::Variable(bytes)
+        | Self::Parsed { raw: bytes, .. }
+        | Self::ParseFailed { raw: bytes, .. }) = self
+        else {
+            return None;
+        };
+        if bytes.len() % N != 0 {
+            return None;
+        }
+        Some(
+            bytes
+                .chunks_exact(N)
+                .map(|chunk| chunk.try_into().expect("chunk of N bytes")),
+        )
+    }
+
+    /// Variable length value read as an array of big-endian `u16`
+    ///
+    /// `None` for fixed width values and lengths that are not a multiple of 2.
+    pub fn as_u16_array(&self) -> Option<Vec<u16>> {
+        Some(self.chunked()?.map(u16::from_be_bytes).collect())
+    }
+
+    /// Variable length value read as an array of big-endian `u32`
+    ///
+    /// `None` for fixed width values and lengths that are not a multiple of 4.
+    pub fn as_u32_array(&self) -> Option<Vec<u32>> {
+        Some(self.chunked()?.map(u32::from_be_bytes).collect())
+    }
+
+    /// Variable length value read as an array of big-endian `u64`
+    ///
+    /// `None` for fixed width values and lengths that are not a multiple of 8.
+    pub fn as_u64_array(&self) -> Option<Vec<u64>> {
+        Some(self.chunked()?.map(u64::from_be_bytes).collect())
+    }
+
+    /// Variable length value read as an array of little-endian `u16`, see [`AVLEventIOValue::as_u16_array`]
+    ///
+    /// The protocol is big-endian, only use it for devices known to send little-endian payloads.
+    pub fn as_u16_array_le(&self) -> Option<Vec<u16>> {
+        Some(self.chunked()?.map(u16::from_le_bytes).collect())
+    }
+
+    /// Variable length value read as an array of little-endian `u32`, see [`AVLEventIOValue::as_u32_array`]
+    ///
+    /// The protocol is big-endian, only use it for devices known to send little-endian payloads.
+    pub fn as_u32_array_le(&self) -> Option<Vec<u32>> {
+        Some(self.chunked()?.map(u32::from_le_bytes).collect())
+    }
+
+    /// Variable length value read as an array of little-endian `u64`, see [`AVLEventIOValue::as_u64_array`]
+    ///
+    /// The protocol is big-endian, only use it for devices known to send little-endian payloads.
+    pub fn as_u64_array_le(&self) -> Option<Vec<u64>> {
+        Some(self.chunked()?.map(u64::from_le_bytes).collect())
+    }
+}
+
+/// Message sent by the device on a live connection, see [`TeltonikaStream::read_message`](crate::TeltonikaStream::read_message)
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TeltonikaMessage {
+    /// AVL data, acknowledged with its number of records
+    Avl(AVLFrame),
+    /// Command responses, not acknowledged
+    Command(GPRSFrame),
+    /// Keep-alive sent between frames, not acknowledged
+    KeepAlive,
+}
+
+impl From<TeltonikaFrame> for TeltonikaMessage {
+    fn from(frame: TeltonikaFrame) -> Self {
+        match frame {
+            TeltonikaFrame::AVL(frame) => Self::Avl(frame),
+            TeltonikaFrame::GPRS(frame) => Self::Command(frame),
+            TeltonikaFrame::KeepAlive => Self::KeepAlive,
+        }
+    }
+}
+
+/// Message sent by the server to the device, see [`parser::server_messages`](crate::parser::server_messages)
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ServerMessage {
+    /// Whether the IMEI sent by the device was accepted
+    ImeiApproval(bool),
+    /// Acknowledgment of a frame, see [`AVLFrame::is_ack_accepted`]
+    Ack(u32),
+    /// Commands sent to the device
+    Command(GPRSFrame),
+}
+
+/// Command or response carried by a [`GPRSFrame`], kept as the bytes received
+///
+/// Responses are usually text, but serial passthrough commands can return binary data:
+/// [`CommandResponse::as_str_strict`] tells them apart.
+///
+/// Serialized as a string when it is valid UTF-8, as bytes otherwise.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(from = "CommandResponseRepr", into = "CommandResponseRepr")
+)]
+pub struct CommandResponse(Vec<u8>);
+
+impl CommandResponse {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// The response as text, failing on binary responses
+    pub fn as_str_strict(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.0)
+    }
+
+    /// The response as text, replacing invalid UTF-8 sequences with `U+FFFD`
+    pub fn as_str_lossy(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for CommandResponse {
+    fn from(value: Vec<u8>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&[u8]> for CommandResponse {
+    fn from(value: &[u8]) -> Self {
+        Self(value.to_vec())
+    }
+}
+
+impl From<String> for CommandResponse {
+    fn from(value: String) -> Self {
+        Self(value.into_bytes())
+    }
+}
+
+impl From<&str> for CommandResponse {
+    fn from(value: &str) -> Self {
+        Self(value.as_bytes().to_vec())
+    }
+}
+
+impl AsRef<[u8]> for CommandResponse {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for CommandResponse {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for CommandResponse {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+/// Lossy, see [`CommandResponse::as_str_lossy`]
+impl std::fmt::Display for CommandResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.as_str_lossy())
+    }
+}
+
+/// [`AVLRecord`] whose raw coordinates may be missing
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct AVLRecordRepr {
+    timestamp: DateTime<Utc>,
+    priority: Priority,
+    longitude: f64,
+    latitude: f64,
+    #[serde(default)]
+    longitude_raw: Option<i32>,
+    #[serde(default)]
+    latitude_raw: Option<i32>,
+    altitude: u16,
+    angle: u16,
+    satellites: u8,
+    speed: u16,
+    trigger_event_id: u16,
+    generation_type: Option<EventGenerationCause>,
+    io_events: Vec<AVLEventIO>,
+}
+
+#[cfg(feature = "serde")]
+impl From<AVLRecordRepr> for AVLRecord {
+    fn from(value: AVLRecordRepr) -> Self {
+        let mut record = AVLRecord {
+            timestamp: value.timestamp,
+            priority: value.priority,
+            longitude: value.longitude,
+            latitude: value.latitude,
+            longitude_raw: value.longitude_raw.unwrap_or_default(),
+            latitude_raw: value.latitude_raw.unwrap_or_default(),
+            altitude: value.altitude,
+            angle: value.angle,
+            satellites: value.satellites,
+            speed: value.speed,
+            trigger_event_id: value.trigger_event_id,
+            generation_type: value.generation_type,
+            io_events: value.io_events,
+        };
+        if value.longitude_raw.is_none() || value.latitude_raw.is_none() {
+            record.set_coordinates(value.longitude, value.latitude);
+        }
+        record
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CommandResponseRepr {
+    Text(String),
+    #[serde(with = "serde_bytes")]
+    Bytes(Vec<u8>),
+}
+
+#[cfg(feature = "serde")]
+impl From<CommandResponseRepr> for CommandResponse {
+    fn from(value: CommandResponseRepr) -> Self {
+        match value {
+            CommandResponseRepr::Text(text) => text.into(),
+            CommandResponseRepr::Bytes(bytes) => bytes.into(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<CommandResponse> for CommandResponseRepr {
+    fn from(value: CommandResponse) -> Self {
+        match String::from_utf8(value.0) {
+            Ok(text) => Self::Text(text),
+            Err(e) => Self::Bytes(e.into_bytes()),
+        }
+    }
 }
 
 /// Frame sent by the device when sending command responses
@@ -211,8 +1133,12 @@ pub enum AVLEventIOValue {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GPRSFrame {
     pub codec: Codec,
+    /// Whether the frame holds commands or responses to them
+    pub command_type: Codec12Type,
     /// All the commands to send with this buffer
-    pub command_responses: Vec<String>,
+    pub command_responses: Vec<CommandResponse>,
+    /// IMEI carried by Codec 14 frames, `None` for Codec 12
+    pub imei: Option<Imei>,
     /// CRC16 Calculated using [IBM/CRC16][super::crc16] algorithm and 0xA001 polynomial
     pub crc16: u32,
 }
@@ -225,20 +1151,121 @@ pub struct GPRSFrame {
 pub enum TeltonikaFrame {
     AVL(AVLFrame),
     GPRS(GPRSFrame),
+    /// Bare zero preamble sent by some devices between frames to keep the connection alive,
+    /// it must not be acknowledged
+    KeepAlive,
 }
 
 impl TeltonikaFrame {
+    /// Parses a complete frame, see [`crate::parser::tcp_frame_complete`]
+    ///
+    /// Bytes following the frame are ignored.
+    ///
+    /// ```rust
+    /// use nom_teltonika::{TeltonikaFrame, TeltonikaParseError};
+    ///
+    /// let input = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0F, 0x0C, 0x01, 0x06];
+    /// assert_eq!(
+    ///     TeltonikaFrame::parse(&input),
+    ///     Err(TeltonikaParseError::Truncated {
+    ///         declared: 27,
+    ///         available: 11
+    ///     })
+    /// );
+    /// assert_eq!(
+    ///     TeltonikaFrame::parse(&[0x00; 4]),
+    ///     Ok(TeltonikaFrame::KeepAlive)
+    /// );
+    /// // Zeros after a preamble are keep-alives, not an empty data field
+    /// assert_eq!(
+    ///     TeltonikaFrame::parse(&[0x00; 12]),
+    ///     Ok(TeltonikaFrame::KeepAlive)
+    /// );
+    /// ```
+    pub fn parse(input: &[u8]) -> Result<Self, TeltonikaParseError> {
+        tcp_frame_complete(input).map(|(_, frame)| frame)
+    }
+
+    /// Number of AVL records, 0 for GPRS frames and keep-alives
+    pub fn record_count(&self) -> usize {
+        match self {
+            Self::AVL(frame) => frame.records.len(),
+            Self::GPRS(_) | Self::KeepAlive => 0,
+        }
+    }
+
     pub fn unwrap_avl(self) -> AVLFrame {
         if let Self::AVL(frame) = self {
             return frame;
         }
-        panic!("Frame is GPRS!")
+        panic!("Frame is not AVL!")
     }
 
     pub fn unwrap_gprs(self) -> GPRSFrame {
         if let Self::GPRS(frame) = self {
             return frame;
         }
-        panic!("Frame is AVL!")
+        panic!("Frame is not GPRS!")
+    }
+}
+
+/// Frames read from a connection, kept apart by kind for routing them downstream
+///
+/// Keep-alives carry nothing and are dropped.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FrameBatch {
+    avl: Vec<AVLFrame>,
+    gprs: Vec<GPRSFrame>,
+}
+
+impl FrameBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, frame: TeltonikaFrame) {
+        match frame {
+            TeltonikaFrame::AVL(frame) => self.avl.push(frame),
+            TeltonikaFrame::GPRS(frame) => self.gprs.push(frame),
+            TeltonikaFrame::KeepAlive => {}
+        }
+    }
+
+    pub fn avl_frames(&self) -> &[AVLFrame] {
+        &self.avl
+    }
+
+    pub fn gprs_frames(&self) -> &[GPRSFrame] {
+        &self.gprs
+    }
+
+    /// Records of all the AVL frames, in the order they were pushed
+    pub fn avl_records(&self) -> impl Iterator<Item = &AVLRecord> {
+        self.avl.iter().flat_map(|frame| &frame.records)
+    }
+
+    /// Records of the AVL frames sent with `codec`, in the order they were pushed
+    pub fn avl_records_with_codec(&self, codec: Codec) -> impl Iterator<Item = &AVLRecord> {
+        self.avl
+            .iter()
+            .filter(move |frame| frame.codec == codec)
+            .flat_map(|frame| &frame.records)
+    }
+
+    /// Command responses of all the GPRS frames, in the order they were pushed
+    pub fn gprs_responses(&self) -> impl Iterator<Item = &CommandResponse> {
+        self.gprs.iter().flat_map(|frame| &frame.command_responses)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.avl.is_empty() && self.gprs.is_empty()
+    }
+}
+
+impl Extend<TeltonikaFrame> for FrameBatch {
+    fn extend<T: IntoIterator<Item = TeltonikaFrame>>(&mut self, frames: T) {
+        for frame in frames {
+            self.push(frame);
+        }
     }
 }
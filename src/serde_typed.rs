@@ -0,0 +1,75 @@
+//! Language neutral serde representation of [`AVLEventIOValue`]
+//!
+//! Values are written as `{"type": "u16", "value": 123}`, variable length ones
+//! as `{"type": "variable", "value": "AQI="}` with the bytes in standard base64.
+//! Parsed variable length ones keep their raw bytes next to the decoded value:
+//! `{"type": "parsed", "value": {"raw": "AQI=", "value": ...}}`,
+//! and so do the ones their parser failed on, next to the error:
+//! `{"type": "parse_failed", "value": {"raw": "AQI=", "error": "..."}}`.
+//!
+//! Opt in with `#[serde(with = "nom_teltonika::serde_typed")]` on the fields holding the values,
+//! the representation of [`AVLEventIO`](crate::AVLEventIO) and the frames is left as is.
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{AVLEventIOValue, ParsedVariable, VariableParseError};
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+enum Typed {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Variable(String),
+    Parsed {
+        raw: String,
+        value: ParsedVariable,
+    },
+    #[serde(rename = "parse_failed")]
+    ParseFailed {
+        raw: String,
+        error: VariableParseError,
+    },
+}
+
+pub fn serialize<S: Serializer>(value: &AVLEventIOValue, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        AVLEventIOValue::U8(v) => Typed::U8(*v),
+        AVLEventIOValue::U16(v) => Typed::U16(*v),
+        AVLEventIOValue::U32(v) => Typed::U32(*v),
+        AVLEventIOValue::U64(v) => Typed::U64(*v),
+        AVLEventIOValue::Variable(bytes) => Typed::Variable(STANDARD.encode(bytes)),
+        AVLEventIOValue::Parsed { raw, value } => Typed::Parsed {
+            raw: STANDARD.encode(raw),
+            value: value.clone(),
+        },
+        AVLEventIOValue::ParseFailed { raw, error } => Typed::ParseFailed {
+            raw: STANDARD.encode(raw),
+            error: error.clone(),
+        },
+    }
+    .serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<AVLEventIOValue, D::Error> {
+    Ok(match Typed::deserialize(deserializer)? {
+        Typed::U8(v) => AVLEventIOValue::U8(v),
+        Typed::U16(v) => AVLEventIOValue::U16(v),
+        Typed::U32(v) => AVLEventIOValue::U32(v),
+        Typed::U64(v) => AVLEventIOValue::U64(v),
+        Typed::Variable(base64) => {
+            AVLEventIOValue::Variable(STANDARD.decode(base64).map_err(D::Error::custom)?)
+        }
+        Typed::Parsed { raw, value } => AVLEventIOValue::Parsed {
+            raw: STANDARD.decode(raw).map_err(D::Error::custom)?,
+            value,
+        },
+        Typed::ParseFailed { raw, error } => AVLEventIOValue::ParseFailed {
+            raw: STANDARD.decode(raw).map_err(D::Error::custom)?,
+            error,
+        },
+    })
+}
@@ -0,0 +1,171 @@
+use std::io;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{frame_ack, Imei, TeltonikaFrame, TeltonikaStream};
+
+/// Frame read by a [`TeltonikaSession`], tagged with the IMEI of the device that sent it
+#[derive(Debug, PartialEq, Clone)]
+pub struct SessionFrame {
+    pub imei: Imei,
+    pub frame: TeltonikaFrame,
+}
+
+/// State of the connection with a single device, on top of a [`TeltonikaStream`]
+///
+/// The IMEI read during the handshake is kept and tags every following frame,
+/// along with counters of the frames received and acknowledged.
+pub struct TeltonikaSession<S> {
+    stream: TeltonikaStream<S>,
+    imei: Option<Imei>,
+    frames_received: u64,
+    frames_acked: u64,
+    last_ack: Option<u32>,
+}
+
+impl<S> TeltonikaSession<S> {
+    pub fn new(stream: TeltonikaStream<S>) -> Self {
+        Self {
+            stream,
+            imei: None,
+            frames_received: 0,
+            frames_acked: 0,
+            last_ack: None,
+        }
+    }
+
+    /// Unwraps the stream, dropping the session state.
+    pub fn into_inner(self) -> TeltonikaStream<S> {
+        self.stream
+    }
+
+    pub fn stream(&self) -> &TeltonikaStream<S> {
+        &self.stream
+    }
+
+    pub fn stream_mut(&mut self) -> &mut TeltonikaStream<S> {
+        &mut self.stream
+    }
+
+    /// IMEI read during the handshake, `None` before it
+    pub fn imei(&self) -> Option<&Imei> {
+        self.imei.as_ref()
+    }
+
+    /// AVL and GPRS frames read so far, keep-alives excluded
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received
+    }
+
+    /// Frames acknowledged so far, ACKs asking for a resend excluded
+    pub fn frames_acked(&self) -> u64 {
+        self.frames_acked
+    }
+
+    /// Last ACK written, 0 asking for a resend
+    pub fn last_ack(&self) -> Option<u32> {
+        self.last_ack
+    }
+
+    /// IMEI to tag the frames with, checked before reading them so that none is consumed without it
+    fn connected_imei(&self) -> io::Result<Imei> {
+        self.imei.clone().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotConnected, "Frame read before the IMEI")
+        })
+    }
+
+    fn tag_frame(&mut self, imei: Imei, frame: TeltonikaFrame) -> SessionFrame {
+        if frame != TeltonikaFrame::KeepAlive {
+            self.frames_received += 1;
+        }
+        SessionFrame { imei, frame }
+    }
+
+    /// Updates the counters once the ACK of `frame` has been written
+    fn count_ack(&mut self, frame: Option<&TeltonikaFrame>) {
+        let Some(ack) = frame_ack(frame) else {
+            return;
+        };
+        if frame.is_some() {
+            self.frames_acked += 1;
+        }
+        self.last_ack = Some(ack);
+    }
+}
+
+impl<S: io::Read + io::Write> TeltonikaSession<S> {
+    /// Reads the IMEI of the device, see [`TeltonikaStream::read_imei`], and keeps it for the following frames.
+    pub fn read_imei(&mut self) -> io::Result<Imei> {
+        let imei = self.stream.read_imei()?;
+        self.imei = Some(imei.clone());
+        Ok(imei)
+    }
+
+    /// Reads a frame, see [`TeltonikaStream::read_frame`], tagged with the IMEI of the device.
+    ///
+    /// # Errors
+    ///
+    /// Along with the errors of [`TeltonikaStream::read_frame`],
+    /// if the IMEI has not been read yet an error kind of [`std::io::ErrorKind::NotConnected`] is returned
+    /// without reading anything.
+    pub fn read_frame(&mut self) -> io::Result<SessionFrame> {
+        let imei = self.connected_imei()?;
+        let frame = self.stream.read_frame()?;
+        Ok(self.tag_frame(imei, frame))
+    }
+
+    pub fn write_imei_approval(&mut self) -> io::Result<()> {
+        self.stream.write_imei_approval()
+    }
+
+    pub fn write_imei_denial(&mut self) -> io::Result<()> {
+        self.stream.write_imei_denial()
+    }
+
+    /// Writes the ACK of a frame, see [`TeltonikaStream::write_frame_ack`].
+    pub fn write_frame_ack(&mut self, frame: Option<&TeltonikaFrame>) -> io::Result<()> {
+        self.stream.write_frame_ack(frame)?;
+        self.count_ack(frame);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: AsyncReadExt + AsyncWriteExt + Unpin> TeltonikaSession<S> {
+    /// Reads the IMEI of the device, see [`TeltonikaStream::read_imei_async`], and keeps it for the following frames.
+    pub async fn read_imei_async(&mut self) -> io::Result<Imei> {
+        let imei = self.stream.read_imei_async().await?;
+        self.imei = Some(imei.clone());
+        Ok(imei)
+    }
+
+    /// Reads a frame, see [`TeltonikaStream::read_frame_async`], tagged with the IMEI of the device.
+    ///
+    /// # Errors
+    ///
+    /// See [`TeltonikaSession::read_frame`].
+    pub async fn read_frame_async(&mut self) -> io::Result<SessionFrame> {
+        let imei = self.connected_imei()?;
+        let frame = self.stream.read_frame_async().await?;
+        Ok(self.tag_frame(imei, frame))
+    }
+
+    pub async fn write_imei_approval_async(&mut self) -> io::Result<()> {
+        self.stream.write_imei_approval_async().await
+    }
+
+    pub async fn write_imei_denial_async(&mut self) -> io::Result<()> {
+        self.stream.write_imei_denial_async().await
+    }
+
+    /// Writes the ACK of a frame, see [`TeltonikaStream::write_frame_ack_async`].
+    pub async fn write_frame_ack_async(
+        &mut self,
+        frame: Option<&TeltonikaFrame>,
+    ) -> io::Result<()> {
+        self.stream.write_frame_ack_async(frame).await?;
+        self.count_ack(frame);
+        Ok(())
+    }
+}
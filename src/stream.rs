@@ -1,9 +1,23 @@
-use std::io;
+use std::{
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use nom::IResult;
 
 #[cfg(feature = "tokio")]
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use crate::{AVLDatagram, Codec, TeltonikaFrame};
+use crate::{
+    encoder::encode_record_count,
+    parser::{
+        tcp_frame_complete_with_options, tcp_frame_with_options, udp_datagram_with_options,
+        ParseOptions,
+    },
+    AVLDatagram, AVLFrame, Codec, Codec12Type, CommandCodec, CommandResponse, GPRSFrame, Imei,
+    ImeiFormat, TeltonikaFrame, TeltonikaMessage, TeltonikaParseError, ValidationError,
+};
 
 const DEFAULT_IMEI_BUF_CAPACITY: usize = 128;
 const DEFAULT_PACKET_BUF_CAPACITY: usize = 2048;
@@ -13,6 +27,15 @@ pub struct TeltonikaStream<S> {
     inner: S,
     imei_buf_capacity: usize,
     packet_buf_capacity: usize,
+    /// Bytes read from `inner` that have not been parsed yet
+    buffer: Vec<u8>,
+    read_timeout: Option<Duration>,
+    accept_gprs: bool,
+    /// Shared with the parser of each read instead of being cloned
+    parse_options: Arc<ParseOptions>,
+    imei_format: ImeiFormat,
+    /// IMEI read from the device, see [`TeltonikaStream::imei`]
+    imei: Option<Imei>,
 }
 
 impl<S> TeltonikaStream<S> {
@@ -22,6 +45,12 @@ impl<S> TeltonikaStream<S> {
             inner,
             imei_buf_capacity: DEFAULT_IMEI_BUF_CAPACITY,
             packet_buf_capacity: DEFAULT_PACKET_BUF_CAPACITY,
+            buffer: Vec::new(),
+            read_timeout: None,
+            accept_gprs: true,
+            parse_options: Arc::default(),
+            imei_format: ImeiFormat::default(),
+            imei: None,
         }
     }
 
@@ -33,6 +62,9 @@ impl<S> TeltonikaStream<S> {
         stream
     }
 
+    /// Unwraps the inner stream.
+    ///
+    /// Bytes already read from the stream but not parsed yet are lost.
     pub fn into_inner(self) -> S {
         self.inner
     }
@@ -43,132 +75,546 @@ impl<S> TeltonikaStream<S> {
     pub fn inner_mut(&mut self) -> &mut S {
         &mut self.inner
     }
-}
 
-impl<S: io::Read + io::Write> TeltonikaStream<S> {
-    /// Reads the IMEI (International Mobile Equipment Identity) from the stream.
-    /// Returns the IMEI as a string.
+    /// Bytes received but not parsed yet, e.g. a frame sent along with the IMEI
     ///
-    /// # Errors
+    /// They are parsed by the next read before reading the inner stream again.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Sets how long a read can go on without receiving any byte, `None` waits forever.
     ///
-    /// If this function encounters any form of I/O or other error, an error variant will be returned as in [`Read::read`].
+    /// When the window elapses the read fails with [`std::io::ErrorKind::TimedOut`],
+    /// keeping the bytes received so far for the next read.
     ///
-    /// If no bytes are read from the stream, an error kind of [`std::io::ErrorKind::ConnectionReset`] is returned.
-    /// If the IMEI cannot be parsed, an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
-    pub fn read_imei(&mut self) -> io::Result<String> {
-        let mut parse_buf: Vec<u8> = Vec::with_capacity(self.imei_buf_capacity * 2);
+    /// A blocking read on the inner stream cannot be interrupted, so with the synchronous methods
+    /// the inner stream must return periodically for the timeout to be enforced:
+    /// either configure it as non-blocking or give it its own timeout
+    /// (e.g. [`std::net::TcpStream::set_read_timeout`]).
+    /// [`std::io::ErrorKind::WouldBlock`] and [`std::io::ErrorKind::TimedOut`] errors of the inner stream
+    /// are then retried until the window elapses.
+    ///
+    /// The asynchronous methods wrap each read in [`tokio::time::timeout`],
+    /// which requires the tokio time driver to be enabled.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
 
-        // Read bytes until they are enough
-        loop {
-            let mut recv_buf = vec![0u8; self.imei_buf_capacity];
-            let bytes_read = self.inner.read(&mut recv_buf[..])?;
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
 
-            if bytes_read == 0 {
-                return Err(io::Error::new(
-                    io::ErrorKind::ConnectionReset,
-                    "Connection closed",
-                ));
-            }
+    /// Sets whether reading a GPRS frame is allowed, `true` by default.
+    ///
+    /// Servers only expecting AVL data can disable it so that GPRS frames
+    /// fail with [`std::io::ErrorKind::InvalidData`] instead of being returned.
+    pub fn set_accept_gprs(&mut self, accept_gprs: bool) {
+        self.accept_gprs = accept_gprs;
+    }
 
-            parse_buf.extend_from_slice(&recv_buf[..bytes_read]);
+    pub fn accept_gprs(&self) -> bool {
+        self.accept_gprs
+    }
 
-            let frame_parser_result = crate::parser::imei(&parse_buf[..]);
+    /// Sets the [`ParseOptions`] frames and datagrams are read with.
+    ///
+    /// Frames exceeding [`ParseOptions::max_records`] or [`ParseOptions::max_io_per_record`]
+    /// fail with [`std::io::ErrorKind::InvalidData`], limiting what untrusted devices can make the server allocate.
+    pub fn set_parse_options(&mut self, parse_options: ParseOptions) {
+        self.parse_options = Arc::new(parse_options);
+    }
 
-            match frame_parser_result {
-                Ok((_, imei)) => return Ok(imei),
-                Err(nom::Err::Incomplete(_)) => continue,
-                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
-                    ))
-                }
+    pub fn parse_options(&self) -> &ParseOptions {
+        &self.parse_options
+    }
+
+    /// Sets how the IMEI sent by the device is encoded,
+    /// [`ImeiFormat::LengthPrefixedAscii`] by default as the specification requires.
+    pub fn set_imei_format(&mut self, imei_format: ImeiFormat) {
+        self.imei_format = imei_format;
+    }
+
+    pub fn imei_format(&self) -> ImeiFormat {
+        self.imei_format
+    }
+
+    /// IMEI returned by the last successful [`TeltonikaStream::read_imei`],
+    /// recorded on the spans of the reads with the `tracing` feature
+    pub fn imei(&self) -> Option<&Imei> {
+        self.imei.as_ref()
+    }
+
+    /// Takes the keep-alive left alone in the buffer when `error` is a read timeout or the end of the stream,
+    /// nothing followed it so it is not the start of a frame
+    fn take_bare_keep_alive(&mut self, error: io::Error) -> io::Result<TeltonikaFrame> {
+        if matches!(
+            error.kind(),
+            io::ErrorKind::TimedOut | io::ErrorKind::UnexpectedEof
+        ) && self.buffer == [0x00; 4]
+        {
+            self.buffer.clear();
+            return Ok(TeltonikaFrame::KeepAlive);
+        }
+        Err(error)
+    }
+
+    /// Parses the bytes buffered so far as a frame, without reading more nor consuming them,
+    /// e.g. to report what was received once the connection closed mid-frame.
+    ///
+    /// Returns `None` when nothing is buffered.
+    ///
+    /// # Errors
+    ///
+    /// A frame cut short fails with [`TeltonikaParseError::Truncated`], see [`crate::parser::tcp_frame_complete`].
+    pub fn try_parse_buffered(&self) -> Result<Option<TeltonikaFrame>, TeltonikaParseError> {
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        tcp_frame_complete_with_options(&self.buffer, &self.parse_options)
+            .map(|(_, frame)| Some(frame))
+    }
+
+    /// Takes a whole frame out of the buffered bytes without reading more, `None` if there is none
+    fn take_buffered_frame(&mut self) -> io::Result<Option<TeltonikaFrame>> {
+        let parser = tcp_frame_with_options(Arc::clone(&self.parse_options));
+        match self.parse_buffered(parser) {
+            Some(frame) => self.check_frame(frame?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Rejects GPRS frames when they are not accepted
+    fn check_frame(&self, frame: TeltonikaFrame) -> io::Result<TeltonikaFrame> {
+        match frame {
+            TeltonikaFrame::GPRS(_) if !self.accept_gprs => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Received a GPRS frame while only AVL frames are accepted",
+            )),
+            frame => Ok(frame),
+        }
+    }
+
+    /// Parses the buffered bytes, removing the ones consumed by `parser`.
+    ///
+    /// Returns `None` when more bytes are needed, on errors the bytes of the bad frame are dropped,
+    /// see [`bad_frame_len`].
+    fn parse_buffered<T>(
+        &mut self,
+        parser: impl Fn(&[u8]) -> IResult<&[u8], T>,
+    ) -> Option<io::Result<T>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        match parser(&self.buffer[..]) {
+            Ok((rest, value)) => {
+                let consumed = self.buffer.len() - rest.len();
+                self.buffer.drain(..consumed);
+                Some(Ok(value))
+            }
+            Err(nom::Err::Incomplete(_)) => None,
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    kind = ?e.code,
+                    offset = self.buffer.len() - e.input.len(),
+                    "Failed to parse the received bytes"
+                );
+                let error = io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
+                );
+                self.buffer.drain(..bad_frame_len(&self.buffer));
+                Some(Err(error))
             }
         }
     }
+}
 
-    /// Reads an AVLFrame from the stream.
-    /// Returns the parsed AVLFrame.
+/// Number of bytes to drop from `buffer` after failing to parse the frame at its start
+///
+/// A whole frame is dropped as declared by its data length, otherwise bytes are dropped
+/// up to the next preamble, or all of them if there is none.
+fn bad_frame_len(buffer: &[u8]) -> usize {
+    if let [0, 0, 0, 0, a, b, c, d, ..] = *buffer {
+        let frame_len = (u32::from_be_bytes([a, b, c, d]) as usize).saturating_add(12);
+        if frame_len <= buffer.len() {
+            return frame_len;
+        }
+    }
+    (1..buffer.len())
+        .find(|&start| buffer[start..].starts_with(&[0x00; 4]))
+        .unwrap_or(buffer.len())
+}
+
+/// ACK of a frame: its number of records or command responses, 0 for no frame
+///
+/// Keep-alives are not acknowledged
+pub(crate) fn frame_ack(frame: Option<&TeltonikaFrame>) -> Option<u32> {
+    match frame {
+        Some(TeltonikaFrame::AVL(avlframe)) => Some(avlframe.records.len() as u32),
+        Some(TeltonikaFrame::GPRS(gprsframe)) => Some(gprsframe.command_responses.len() as u32),
+        Some(TeltonikaFrame::KeepAlive) => None,
+        None => Some(0),
+    }
+}
+
+/// ACKs of a batch of frames, one after the other
+fn frame_acks(frames: &[&TeltonikaFrame]) -> Vec<u8> {
+    frames
+        .iter()
+        .filter_map(|frame| frame_ack(Some(frame)))
+        .flat_map(u32::to_be_bytes)
+        .collect()
+}
+
+/// Responses read by [`TeltonikaStream::send_commands`], along with the AVL frames received before them
+#[derive(Debug, PartialEq, Clone)]
+pub struct CommandExchange {
+    /// Each command paired with the response at the same position
+    pub responses: Vec<(String, CommandResponse)>,
+    /// [`TeltonikaFrame::AVL`] frames the device sent before the responses, in order.
+    ///
+    /// They are not ACKed yet, see [`TeltonikaStream::write_frame_acks`]
+    pub avl_frames: Vec<TeltonikaFrame>,
+}
+
+/// Pairs each command with the response at the same position
+fn pair_responses(
+    commands: &[&str],
+    frame: GPRSFrame,
+) -> io::Result<Vec<(String, CommandResponse)>> {
+    if frame.command_responses.len() != commands.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Received {} responses to {} commands",
+                frame.command_responses.len(),
+                commands.len()
+            ),
+        ));
+    }
+    Ok(commands
+        .iter()
+        .map(|command| command.to_string())
+        .zip(frame.command_responses)
+        .collect())
+}
+
+/// ACK of a datagram, all zeroes for no datagram
+fn datagram_ack(datagram: Option<&AVLDatagram>) -> Vec<u8> {
+    let (length, packet_id, avl_packet_id, ack) = match datagram {
+        Some(datagram) => (
+            datagram.records.len() as u16,
+            datagram.packet_id,
+            datagram.avl_packet_id,
+            datagram.records.len() as u32,
+        ),
+        None => (0, 0, 0, 0),
+    };
+    let mut buffer = Vec::with_capacity(10);
+    buffer.extend(length.to_be_bytes());
+    buffer.extend(packet_id.to_be_bytes());
+    buffer.push(0x01); // Non usable-byte
+    buffer.push(avl_packet_id);
+    buffer.extend(ack.to_be_bytes());
+    buffer
+}
+
+/// Sends the ACK of a datagram to the address it was received from.
+///
+/// UDP is connectionless, so unlike [`TeltonikaStream::write_datagram_ack_async`]
+/// the ACK is sent with [`tokio::net::UdpSocket::send_to`] to `target`,
+/// the address returned by [`tokio::net::UdpSocket::recv_from`] along with the datagram.
+/// If `datagram` is `None`, writes a zero value.
+#[cfg(feature = "tokio")]
+pub async fn send_datagram_ack(
+    socket: &tokio::net::UdpSocket,
+    target: std::net::SocketAddr,
+    datagram: Option<&AVLDatagram>,
+) -> io::Result<()> {
+    socket.send_to(&datagram_ack(datagram), target).await?;
+    Ok(())
+}
+
+/// Checks the commands fit a GPRS frame, see [`encode_commands_with_codec`]
+fn validate_commands(codec: &CommandCodec, commands: &[&str]) -> Result<(), ValidationError> {
+    if commands.len() > u8::MAX as usize {
+        return Err(ValidationError::TooManyCommands(commands.len()));
+    }
+    let prefix_len = match codec {
+        CommandCodec::C12 => 0,
+        CommandCodec::C13 { .. } => std::mem::size_of::<u32>(),
+        CommandCodec::C14 { imei } => imei.to_bcd().len(),
+    };
+    if let Some(command) = commands
+        .iter()
+        .position(|command| u32::try_from(prefix_len + command.len()).is_err())
+    {
+        return Err(ValidationError::CommandTooLong { command });
+    }
+    // Codec, counts and command type, then the size and the prefix of each command
+    let data_len = commands.iter().fold(4usize, |len, command| {
+        len.saturating_add(std::mem::size_of::<u32>() + prefix_len + command.len())
+    });
+    if u32::try_from(data_len).is_err() {
+        return Err(ValidationError::DataTooLong(data_len));
+    }
+    Ok(())
+}
+
+/// Encodes a Codec 12 GPRS frame holding `commands`, the bytes [`TeltonikaStream::write_commands`] sends
+///
+/// # Errors
+///
+/// Fails as [`encode_commands_with_codec`] does.
+pub fn encode_commands(commands: &[&str]) -> Result<Vec<u8>, ValidationError> {
+    encode_commands_with_codec(&CommandCodec::C12, commands)
+}
+
+/// Encodes a GPRS frame holding `commands`, the bytes [`TeltonikaStream::write_commands_with_codec`] sends
+///
+/// # Errors
+///
+/// More than 255 commands, a command longer than `u32::MAX` with its prefix,
+/// or commands adding up to a data field longer than `u32::MAX` cannot be encoded.
+pub fn encode_commands_with_codec(
+    codec: &CommandCodec,
+    commands: &[&str],
+) -> Result<Vec<u8>, ValidationError> {
+    validate_commands(codec, commands)?;
+
+    let prefix = match codec {
+        CommandCodec::C12 => vec![],
+        CommandCodec::C13 { timestamp } => (timestamp.timestamp() as u32).to_be_bytes().to_vec(),
+        CommandCodec::C14 { imei } => imei.to_bcd().to_vec(),
+    };
+
+    let data_size: usize = std::mem::size_of::<Codec>() + // codec
+        codec.codec().record_count_width() + // command qty1
+        std::mem::size_of::<u8>() + // command type
+        commands
+            .iter()
+            .fold(0, |acc, e| acc + (std::mem::size_of::<u32>() + prefix.len() + e.len())) + // command size + prefix + command string
+        codec.codec().record_count_width(); // command qty2
+
+    let header_size = std::mem::size_of::<u32>() + // preamble
+        std::mem::size_of::<u32>(); // data size
+    let buffer_size = header_size + data_size + std::mem::size_of::<u32>(); // CRC 16
+
+    let mut commands_buffer = Vec::with_capacity(buffer_size);
+    commands_buffer.extend([0x00, 0x00, 0x00, 0x00].iter()); // preamble
+    commands_buffer.extend((data_size as u32).to_be_bytes().iter()); // data size
+    commands_buffer.push(codec.codec().into()); // codec
+    encode_record_count(codec.codec(), commands.len(), &mut commands_buffer); // Qty1
+    commands_buffer.push(Codec12Type::Command.into()); // Command type
+    commands_buffer.extend(commands.iter().flat_map(|command| {
+        let mut command_buffer =
+            Vec::with_capacity(std::mem::size_of::<u32>() + prefix.len() + command.len());
+
+        command_buffer.extend(((prefix.len() + command.len()) as u32).to_be_bytes());
+        command_buffer.extend(&prefix);
+        command_buffer.extend(command.bytes()); // no call to to_be_bytes needed because it writes single bytes
+
+        command_buffer
+    }));
+    encode_record_count(codec.codec(), commands.len(), &mut commands_buffer); // Qty2
+    commands_buffer.extend(
+        (crate::crc16(&commands_buffer[header_size..]) as u32)
+            .to_be_bytes()
+            .iter(),
+    ); // crc 16
+
+    Ok(commands_buffer)
+}
+
+/// Records the codec and the number of records of a frame in the current span
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn trace_frame(frame: &TeltonikaFrame) {
+    #[cfg(feature = "tracing")]
+    {
+        let (codec, records) = match frame {
+            TeltonikaFrame::AVL(frame) => (frame.codec, frame.records.len()),
+            TeltonikaFrame::GPRS(frame) => (frame.codec, frame.command_responses.len()),
+            TeltonikaFrame::KeepAlive => return,
+        };
+        let span = tracing::Span::current();
+        span.record("codec", tracing::field::debug(codec));
+        span.record("records", records);
+    }
+}
+
+fn closed_mid_frame() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "Connection closed in the middle of a frame",
+    )
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        "No bytes received within the read timeout",
+    )
+}
+
+impl<S: io::Read + io::Write> TeltonikaStream<S> {
+    /// Reads the IMEI (International Mobile Equipment Identity) from the stream.
+    /// Returns the validated [`Imei`].
+    ///
+    /// # Errors
+    ///
+    /// If this function encounters any form of I/O or other error, an error variant will be returned as in [`Read::read`].
+    ///
+    /// If no bytes are read from the stream, an error kind of [`std::io::ErrorKind::ConnectionReset`] is returned.
+    /// If the IMEI cannot be parsed, an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
+    ///
+    /// Bytes received along with the IMEI are kept for the next read, see [`TeltonikaStream::buffered`].
+    ///
+    /// The IMEI is decoded as set by [`TeltonikaStream::set_imei_format`].
+    pub fn read_imei(&mut self) -> io::Result<Imei> {
+        let parser = crate::parser::imei_with_format(self.imei_format);
+        let imei = self.read_parsed(self.imei_buf_capacity, true, parser)?;
+        self.imei = Some(imei.clone());
+        Ok(imei)
+    }
+
+    /// Reads a [`TeltonikaFrame`] from the stream.
+    ///
+    /// Devices send both AVL frames and GPRS command responses on the same connection,
+    /// match on the returned frame to handle both, or on the [`TeltonikaMessage`] read by [`TeltonikaStream::read_message`].
+    /// Keep in mind that they are acknowledged differently, see [`TeltonikaStream::write_frame_ack`].
+    ///
+    /// Keep-alives sent between frames are returned as [`TeltonikaFrame::KeepAlive`],
+    /// once the bytes following them are received. A keep-alive the device sent before going idle
+    /// could be the start of a frame, it is returned once the read timeout elapses without more bytes,
+    /// see [`TeltonikaStream::set_read_timeout`].
+    ///
+    /// Bytes following the frame are kept for the next read.
     ///
     /// # Errors
     ///
     /// If this function encounters any form of I/O or other error, an error variant will be returned as in [`Read::read`].
     ///
     /// If no bytes are read from the stream, it either means that a command response of length 0 has been sent or that the stream has been closed.
-    /// If the frame cannot be parsed, an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
+    /// If the stream is closed mid-frame, an error kind of [`std::io::ErrorKind::UnexpectedEof`] is returned
+    /// and the bytes received are kept, see [`TeltonikaStream::try_parse_buffered`].
+    /// If the frame cannot be parsed, or is a GPRS frame while they are not accepted (see [`TeltonikaStream::set_accept_gprs`]),
+    /// an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(imei = self.imei.as_ref().map(Imei::as_str), codec, records)
+        )
+    )]
     pub fn read_frame(&mut self) -> io::Result<TeltonikaFrame> {
-        let mut parse_buf: Vec<u8> = Vec::with_capacity(self.packet_buf_capacity * 2);
-
-        // Read bytes until they are enough
-        loop {
-            let mut revc_buf = vec![0u8; self.packet_buf_capacity];
-            let bytes_read = self.inner.read(&mut revc_buf)?;
-
-            // Since teltonika devices can send 0 bytes command responses this needs to be removed
-            // if bytes_read == 0 {
-            //     return Err(io::Error::new(
-            //         io::ErrorKind::ConnectionReset,
-            //         "Connection closed",
-            //     ));
-            // }
+        // Since teltonika devices can send 0 bytes command responses reading 0 bytes is not an error
+        let parser = tcp_frame_with_options(Arc::clone(&self.parse_options));
+        let frame = match self.read_parsed(self.packet_buf_capacity, false, parser) {
+            Ok(frame) => frame,
+            Err(e) => self.take_bare_keep_alive(e)?,
+        };
+        trace_frame(&frame);
+        self.check_frame(frame)
+    }
 
-            parse_buf.extend_from_slice(&revc_buf[..bytes_read]);
+    /// Reads the next [`TeltonikaMessage`] a device sends: AVL data, command responses or a keep-alive.
+    ///
+    /// Same as [`TeltonikaStream::read_frame`], with the frame converted into the message it carries.
+    pub fn read_message(&mut self) -> io::Result<TeltonikaMessage> {
+        self.read_frame().map(TeltonikaMessage::from)
+    }
 
-            let frame_parser_result = crate::parser::tcp_frame(&parse_buf[..]);
+    pub fn read_datagram(&mut self) -> io::Result<AVLDatagram> {
+        let parser = udp_datagram_with_options(Arc::clone(&self.parse_options));
+        self.read_parsed(self.packet_buf_capacity, true, parser)
+    }
 
-            match frame_parser_result {
-                Ok((_, frame)) => {
-                    return Ok(frame);
-                }
-                Err(nom::Err::Incomplete(_)) => {
-                    continue;
-                }
-                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
-                    ))
-                }
-            }
-        }
+    /// Reads the ACK sent by the server after a frame, when emulating a device.
+    ///
+    /// The frame was accepted if the ACK matches its number of records, see [`AVLFrame::is_ack_accepted`].
+    ///
+    /// # Errors
+    ///
+    /// If the stream is closed before the 4 bytes are read, an error kind of [`std::io::ErrorKind::ConnectionReset`] is returned.
+    pub fn read_frame_ack(&mut self) -> io::Result<u32> {
+        self.read_parsed(self.imei_buf_capacity, true, crate::parser::frame_ack)
     }
 
-    pub fn read_datagram(&mut self) -> io::Result<AVLDatagram> {
-        let mut parse_buf: Vec<u8> = Vec::with_capacity(self.packet_buf_capacity * 2);
+    /// Reads chunks of `chunk_capacity` bytes until `parser` succeeds.
+    fn read_parsed<T>(
+        &mut self,
+        chunk_capacity: usize,
+        eof_is_error: bool,
+        parser: impl Fn(&[u8]) -> IResult<&[u8], T>,
+    ) -> io::Result<T> {
+        let mut last_progress = Instant::now();
 
         // Read bytes until they are enough
         loop {
-            let mut revc_buf = vec![0u8; self.packet_buf_capacity];
-            let bytes_read = self.inner.read(&mut revc_buf)?;
-
-            if bytes_read == 0 {
-                return Err(io::Error::new(
-                    io::ErrorKind::ConnectionReset,
-                    "Connection closed",
-                ));
+            if let Some(result) = self.parse_buffered(&parser) {
+                return result;
             }
 
-            parse_buf.extend_from_slice(&revc_buf[..bytes_read]);
-
-            let datagram_parser_result = crate::parser::udp_datagram(&parse_buf[..]);
-
-            match datagram_parser_result {
-                Ok((_, datagram)) => {
-                    return Ok(datagram);
-                }
-                Err(nom::Err::Incomplete(_)) => {
-                    continue;
-                }
-                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            // Read straight into the buffer, its capacity is kept across reads
+            let filled = self.buffer.len();
+            self.buffer.resize(filled + chunk_capacity, 0);
+            let read = self.inner.read(&mut self.buffer[filled..]);
+            self.buffer
+                .truncate(filled + read.as_ref().map_or(0, |bytes_read| *bytes_read));
+            let bytes_read = match read {
+                Ok(0) if eof_is_error => {
                     return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
-                    ))
+                        io::ErrorKind::ConnectionReset,
+                        "Connection closed",
+                    ));
+                }
+                Ok(0) if !self.buffer.is_empty() => return Err(closed_mid_frame()),
+                Ok(bytes_read) => bytes_read,
+                Err(e)
+                    if self.read_timeout.is_some()
+                        && matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    0
                 }
+                Err(e) => return Err(e),
+            };
+
+            if bytes_read > 0 {
+                last_progress = Instant::now();
+            } else if self
+                .read_timeout
+                .is_some_and(|timeout| last_progress.elapsed() >= timeout)
+            {
+                return Err(timed_out());
             }
         }
     }
 
+    /// Reads the IMEI and approves it, returning the first frame too if it was sent along with the IMEI.
+    ///
+    /// Devices sending their first frame right after the IMEI, without waiting for the approval,
+    /// have it returned here, otherwise it is left to [`TeltonikaStream::read_frame`]:
+    /// only the bytes already received are checked, nothing more is read after the approval.
+    ///
+    /// Any IMEI is approved, use [`TeltonikaStream::read_imei`] to check it first.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`TeltonikaStream::read_imei`] and [`TeltonikaStream::read_frame`] do.
+    pub fn handshake_and_first_frame(&mut self) -> io::Result<(Imei, Option<TeltonikaFrame>)> {
+        let imei = self.read_imei()?;
+        self.write_imei_approval()?;
+        let frame = self.take_buffered_frame()?;
+        Ok((imei, frame))
+    }
+
     /// Writes an IMEI approval signal to the stream.
     pub fn write_imei_approval(&mut self) -> io::Result<()> {
         self.inner.write_all(&1u8.to_be_bytes())?;
@@ -181,74 +627,67 @@ impl<S: io::Read + io::Write> TeltonikaStream<S> {
         self.inner.flush()
     }
 
+    /// Writes an AVL frame to the stream, when emulating a device.
+    ///
+    /// # Errors
+    ///
+    /// If the frame cannot be encoded, an error kind of [`std::io::ErrorKind::InvalidInput`] is returned.
+    pub fn write_frame(&mut self, frame: &AVLFrame) -> io::Result<()> {
+        let bytes = frame
+            .to_bytes()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.write_all(&bytes)?;
+        self.inner.flush()
+    }
+
     /// Writes a frame ACK (acknowledgment) to the stream.
     /// If `ack` is `None`, writes a zero value.
+    ///
+    /// The ACK of an AVL frame is its number of records,
+    /// the one of a GPRS frame its number of command responses.
+    /// Nothing is written for a keep-alive.
     pub fn write_frame_ack(&mut self, frame: Option<&TeltonikaFrame>) -> io::Result<()> {
-        let ack: u32 = frame
-            .map(|v| match v {
-                TeltonikaFrame::AVL(avlframe) => avlframe.records.len() as u32,
-                TeltonikaFrame::GPRS(gprsframe) => gprsframe.command_responses.len() as u32,
-            })
-            .unwrap_or(0);
+        let Some(ack) = frame_ack(frame) else {
+            return Ok(());
+        };
         self.inner.write_all(&ack.to_be_bytes())?;
         self.inner.flush()
     }
 
+    /// Writes the ACKs of a batch of frames in order, flushing once at the end.
+    ///
+    /// The bytes are the same as calling [`TeltonikaStream::write_frame_ack`] for each frame.
+    pub fn write_frame_acks(&mut self, frames: &[&TeltonikaFrame]) -> io::Result<()> {
+        self.inner.write_all(&frame_acks(frames))?;
+        self.inner.flush()
+    }
+
     pub fn write_datagram_ack(&mut self, datagram: Option<&AVLDatagram>) -> io::Result<()> {
-        let (length, packet_id, avl_packet_id, ack) = match datagram {
-            Some(datagram) => (
-                datagram.records.len() as u16,
-                datagram.packet_id,
-                datagram.avl_packet_id,
-                datagram.records.len() as u32,
-            ),
-            None => (0, 0, 0, 0),
-        };
-        self.inner.write_all(&length.to_be_bytes())?;
-        self.inner.write_all(&packet_id.to_be_bytes())?;
-        self.inner.write_all(b"\x01")?; // Non usable-byte
-        self.inner.write_all(&avl_packet_id.to_be_bytes())?;
-        self.inner.write_all(&ack.to_be_bytes())?;
+        self.inner.write_all(&datagram_ack(datagram))?;
         self.inner.flush()
     }
 
-    /// Writes a series of commands to the stream.
+    /// Writes a series of commands to the stream using Codec 12.
     pub fn write_commands(&mut self, commands: &[&str]) -> io::Result<()> {
-        let data_size: usize = std::mem::size_of::<Codec>() + // codec
-        						std::mem::size_of::<u8>() + // command qty1
-              					std::mem::size_of::<u8>() + // command type
-                   				commands
-                       				.iter()
-                           			.fold(0, |acc, e| acc + (std::mem::size_of::<u32>() + e.bytes().len())) + // command size + command string
-                       			std::mem::size_of::<u8>(); // command qty2
-
-        let header_size = std::mem::size_of::<u32>() + // preamble
-        							std::mem::size_of::<u32>(); // data size
-        let buffer_size = header_size + data_size + std::mem::size_of::<u32>(); // CRC 16
-
-        let mut commands_buffer = Vec::with_capacity(buffer_size);
-        commands_buffer.extend([0x00, 0x00, 0x00, 0x00].iter()); // preamble
-        commands_buffer.extend((data_size as u32).to_be_bytes().iter()); // data size
-        commands_buffer.push(Codec::C12.into()); // codec
-        commands_buffer.push(commands.len() as u8); // Qty1
-        commands_buffer.push(0x05u8); // Command type
-        commands_buffer.extend(commands.iter().flat_map(|command| {
-            let mut command_buffer =
-                Vec::with_capacity(std::mem::size_of::<u32>() + command.bytes().len());
-
-            command_buffer.extend((command.bytes().len() as u32).to_be_bytes());
-            command_buffer.extend(command.bytes()); // no call to to_be_bytes needed because it writes single bytes
-
-            command_buffer
-        }));
-        commands_buffer.push(commands.len() as u8); // Qty2
-        commands_buffer.extend(
-            (crate::crc16(&commands_buffer[header_size..]) as u32)
-                .to_be_bytes()
-                .iter(),
-        ); // crc 16
-
-        self.inner.write_all(&commands_buffer)?;
+        self.write_commands_with_codec(CommandCodec::C12, commands)
+    }
+
+    /// Writes a series of commands to the stream using the given GPRS codec.
+    ///
+    /// Codec 13 and 14 prefix every command with the timestamp or the IMEI they carry.
+    ///
+    /// # Errors
+    ///
+    /// If there are more than 255 commands or one is too long,
+    /// an error kind of [`std::io::ErrorKind::InvalidInput`] is returned.
+    pub fn write_commands_with_codec(
+        &mut self,
+        codec: CommandCodec,
+        commands: &[&str],
+    ) -> io::Result<()> {
+        let bytes = encode_commands_with_codec(&codec, commands)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.write_all(&bytes)?;
         self.inner.flush()
     }
 
@@ -256,12 +695,51 @@ impl<S: io::Read + io::Write> TeltonikaStream<S> {
     pub fn write_command(&mut self, command: &str) -> io::Result<()> {
         self.write_commands(&[command])
     }
+
+    /// Sends commands using Codec 12 and reads their responses,
+    /// pairing each command with the response at the same position.
+    ///
+    /// Keep-alives are skipped until the GPRS frame holding the responses is read.
+    /// AVL frames the device sends meanwhile are kept in [`CommandExchange::avl_frames`] for the caller to ACK.
+    ///
+    /// # Errors
+    ///
+    /// If the number of responses does not match the number of commands,
+    /// an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
+    pub fn send_commands(&mut self, commands: &[&str]) -> io::Result<CommandExchange> {
+        self.write_commands(commands)?;
+        let mut avl_frames = vec![];
+        loop {
+            match self.read_frame()? {
+                TeltonikaFrame::KeepAlive => continue,
+                TeltonikaFrame::GPRS(frame) => {
+                    return Ok(CommandExchange {
+                        responses: pair_responses(commands, frame)?,
+                        avl_frames,
+                    })
+                }
+                frame => avl_frames.push(frame),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncRead> TeltonikaStream<tokio::io::BufReader<S>> {
+    /// Creates a new [`TeltonikaStream`] reading the inner stream through a [`tokio::io::BufReader`].
+    ///
+    /// Each read asks for about the IMEI or packet capacity, the reader fills its 8 KiB buffer instead:
+    /// devices sending many frames at once are read with fewer calls to the inner stream.
+    /// Bytes it reads ahead stay buffered for the next reads, writes go straight to the inner stream.
+    pub fn new_buffered(inner: S) -> Self {
+        Self::new(tokio::io::BufReader::new(inner))
+    }
 }
 
 #[cfg(feature = "tokio")]
 impl<S: AsyncReadExt + AsyncWriteExt + Unpin> TeltonikaStream<S> {
     /// Reads the IMEI (International Mobile Equipment Identity) from the stream.
-    /// Returns the IMEI as a string.
+    /// Returns the validated [`Imei`].
     ///
     /// # Errors
     ///
@@ -269,116 +747,146 @@ impl<S: AsyncReadExt + AsyncWriteExt + Unpin> TeltonikaStream<S> {
     ///
     /// If no bytes are read from the stream, an error kind of [`std::io::ErrorKind::ConnectionReset`] is returned.
     /// If the IMEI cannot be parsed, an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
-    pub async fn read_imei_async(&mut self) -> io::Result<String> {
-        let mut parse_buf: Vec<u8> = Vec::with_capacity(self.imei_buf_capacity * 2);
-
-        // Read bytes until they are enough
-        loop {
-            let mut recv_buf = vec![0u8; self.imei_buf_capacity];
-            let bytes_read = self.inner.read(&mut recv_buf[..]).await?;
-
-            if bytes_read == 0 {
-                return Err(io::Error::new(
-                    io::ErrorKind::ConnectionReset,
-                    "Connection closed",
-                ));
-            }
-
-            parse_buf.extend_from_slice(&recv_buf[..bytes_read]);
-
-            let frame_parser_result = crate::parser::imei(&parse_buf[..]);
-
-            match frame_parser_result {
-                Ok((_, imei)) => return Ok(imei),
-                Err(nom::Err::Incomplete(_)) => continue,
-                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
-                    ))
-                }
-            }
-        }
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe, see [`TeltonikaStream::read_frame_async`].
+    pub async fn read_imei_async(&mut self) -> io::Result<Imei> {
+        let parser = crate::parser::imei_with_format(self.imei_format);
+        let imei = self
+            .read_parsed_async(self.imei_buf_capacity, true, parser)
+            .await?;
+        self.imei = Some(imei.clone());
+        Ok(imei)
     }
 
-    /// Reads an AVLFrame from the stream.
-    /// Returns the parsed AVLFrame.
+    /// Reads a [`TeltonikaFrame`] from the stream.
+    ///
+    /// Devices send both AVL frames and GPRS command responses on the same connection,
+    /// match on the returned frame to handle both, or on the [`TeltonikaMessage`] read by [`TeltonikaStream::read_message_async`].
+    /// Keep in mind that they are acknowledged differently, see [`TeltonikaStream::write_frame_ack_async`].
+    ///
+    /// Keep-alives sent between frames are returned as [`TeltonikaFrame::KeepAlive`],
+    /// once the bytes following them are received. A keep-alive the device sent before going idle
+    /// could be the start of a frame, it is returned once the read timeout elapses without more bytes,
+    /// see [`TeltonikaStream::set_read_timeout`].
+    ///
+    /// Bytes following the frame are kept for the next read.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe: bytes received before the future is dropped
+    /// (e.g. by another branch of `tokio::select!` completing first) are kept,
+    /// and the next read resumes the frame from where it stopped.
     ///
     /// # Errors
     ///
     /// If this function encounters any form of I/O or other error, an error variant will be returned as in [`Read::read`].
     ///
-    /// If no bytes are read from the stream, an error kind of [`std::io::ErrorKind::ConnectionReset`] is returned.
-    /// If the frame cannot be parsed, an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
+    /// If no bytes are read from the stream, it either means that a command response of length 0 has been sent or that the stream has been closed.
+    /// If the stream is closed mid-frame, an error kind of [`std::io::ErrorKind::UnexpectedEof`] is returned
+    /// and the bytes received are kept, see [`TeltonikaStream::try_parse_buffered`].
+    /// If the frame cannot be parsed, or is a GPRS frame while they are not accepted (see [`TeltonikaStream::set_accept_gprs`]),
+    /// an error kind of [`std::io::ErrorKind::InvalidData`] is returned.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(imei = self.imei.as_ref().map(Imei::as_str), codec, records)
+        )
+    )]
     pub async fn read_frame_async(&mut self) -> io::Result<TeltonikaFrame> {
-        let mut parse_buf: Vec<u8> = Vec::with_capacity(self.packet_buf_capacity * 2);
-
-        // Read bytes until they are enough
-        loop {
-            let mut revc_buf = vec![0u8; self.packet_buf_capacity];
-            let bytes_read = self.inner.read(&mut revc_buf).await?;
-
-            // Since teltonika devices can send 0 bytes command responses this needs to be removed
-            // if bytes_read == 0 {
-            //     return Err(io::Error::new(
-            //         io::ErrorKind::ConnectionReset,
-            //         "Connection closed",
-            //     ));
-            // }
+        // Since teltonika devices can send 0 bytes command responses reading 0 bytes is not an error
+        let parser = tcp_frame_with_options(Arc::clone(&self.parse_options));
+        let frame = match self
+            .read_parsed_async(self.packet_buf_capacity, false, parser)
+            .await
+        {
+            Ok(frame) => frame,
+            Err(e) => self.take_bare_keep_alive(e)?,
+        };
+        trace_frame(&frame);
+        self.check_frame(frame)
+    }
 
-            parse_buf.extend_from_slice(&revc_buf[..bytes_read]);
+    /// Reads the next [`TeltonikaMessage`] a device sends, see [`TeltonikaStream::read_message`].
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe, see [`TeltonikaStream::read_frame_async`].
+    pub async fn read_message_async(&mut self) -> io::Result<TeltonikaMessage> {
+        self.read_frame_async().await.map(TeltonikaMessage::from)
+    }
 
-            let frame_parser_result = crate::parser::tcp_frame(&parse_buf[..]);
+    /// Reads an [`AVLDatagram`] from the stream.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe, see [`TeltonikaStream::read_frame_async`].
+    pub async fn read_datagram_async(&mut self) -> io::Result<AVLDatagram> {
+        let parser = udp_datagram_with_options(Arc::clone(&self.parse_options));
+        self.read_parsed_async(self.packet_buf_capacity, true, parser)
+            .await
+    }
 
-            match frame_parser_result {
-                Ok((_, frame)) => {
-                    return Ok(frame);
-                }
-                Err(nom::Err::Incomplete(_)) => {
-                    continue;
-                }
-                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
-                    ))
-                }
-            }
-        }
+    /// Reads the ACK sent by the server after a frame, when emulating a device.
+    ///
+    /// The frame was accepted if the ACK matches its number of records, see [`AVLFrame::is_ack_accepted`].
+    ///
+    /// # Errors
+    ///
+    /// If the stream is closed before the 4 bytes are read, an error kind of [`std::io::ErrorKind::ConnectionReset`] is returned.
+    pub async fn read_frame_ack_async(&mut self) -> io::Result<u32> {
+        self.read_parsed_async(self.imei_buf_capacity, true, crate::parser::frame_ack)
+            .await
     }
 
-    pub async fn read_datagram_async(&mut self) -> io::Result<AVLDatagram> {
-        let mut parse_buf: Vec<u8> = Vec::with_capacity(self.packet_buf_capacity * 2);
+    /// Reads chunks of `chunk_capacity` bytes until `parser` succeeds.
+    async fn read_parsed_async<T>(
+        &mut self,
+        chunk_capacity: usize,
+        eof_is_error: bool,
+        parser: impl Fn(&[u8]) -> IResult<&[u8], T>,
+    ) -> io::Result<T> {
+        let mut last_progress = Instant::now();
 
         // Read bytes until they are enough
         loop {
-            let mut revc_buf = vec![0u8; self.packet_buf_capacity];
-            let bytes_read = self.inner.read(&mut revc_buf).await?;
+            if let Some(result) = self.parse_buffered(&parser) {
+                return result;
+            }
+
+            // Read straight into the spare capacity of the buffer, kept across reads:
+            // it only grows by the bytes read, so cancelling the read leaves it untouched
+            self.buffer.reserve(chunk_capacity);
+            let read = self.inner.read_buf(&mut self.buffer);
+            let bytes_read = match self.read_timeout {
+                Some(timeout) => {
+                    tokio::time::timeout(timeout.saturating_sub(last_progress.elapsed()), read)
+                        .await
+                        .map_err(|_| timed_out())??
+                }
+                None => read.await?,
+            };
 
-            if bytes_read == 0 {
+            if bytes_read == 0 && eof_is_error {
                 return Err(io::Error::new(
                     io::ErrorKind::ConnectionReset,
                     "Connection closed",
                 ));
             }
+            if bytes_read == 0 && !self.buffer.is_empty() {
+                return Err(closed_mid_frame());
+            }
 
-            parse_buf.extend_from_slice(&revc_buf[..bytes_read]);
-
-            let datagram_parser_result = crate::parser::udp_datagram(&parse_buf[..]);
-
-            match datagram_parser_result {
-                Ok((_, datagram)) => {
-                    return Ok(datagram);
-                }
-                Err(nom::Err::Incomplete(_)) => {
-                    continue;
-                }
-                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        nom::Err::Failure(nom::error::Error::new(e.input.to_owned(), e.code)),
-                    ))
-                }
+            if bytes_read > 0 {
+                last_progress = Instant::now();
+            } else if self
+                .read_timeout
+                .is_some_and(|timeout| last_progress.elapsed() >= timeout)
+            {
+                return Err(timed_out());
             }
         }
     }
@@ -395,81 +903,85 @@ impl<S: AsyncReadExt + AsyncWriteExt + Unpin> TeltonikaStream<S> {
         self.inner.flush().await
     }
 
+    /// Reads the IMEI and approves it, returning the first frame too if it was sent along with the IMEI,
+    /// see [`TeltonikaStream::handshake_and_first_frame`].
+    pub async fn handshake_and_first_frame_async(
+        &mut self,
+    ) -> io::Result<(Imei, Option<TeltonikaFrame>)> {
+        let imei = self.read_imei_async().await?;
+        self.write_imei_approval_async().await?;
+        let frame = self.take_buffered_frame()?;
+        Ok((imei, frame))
+    }
+
+    /// Writes an AVL frame to the stream, when emulating a device.
+    ///
+    /// # Errors
+    ///
+    /// If the frame cannot be encoded, an error kind of [`std::io::ErrorKind::InvalidInput`] is returned.
+    pub async fn write_frame_async(&mut self, frame: &AVLFrame) -> io::Result<()> {
+        let bytes = frame
+            .to_bytes()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.write_all(&bytes).await?;
+        self.inner.flush().await
+    }
+
     /// Writes a frame ACK (acknowledgment) to the stream.
     /// If `ack` is `None`, writes a zero value.
+    ///
+    /// The ACK of an AVL frame is its number of records,
+    /// the one of a GPRS frame its number of command responses.
+    /// Nothing is written for a keep-alive.
     pub async fn write_frame_ack_async(
         &mut self,
         frame: Option<&TeltonikaFrame>,
     ) -> io::Result<()> {
-        let ack: u32 = frame
-            .map(|v| match v {
-                TeltonikaFrame::AVL(avlframe) => avlframe.records.len() as u32,
-                TeltonikaFrame::GPRS(gprsframe) => gprsframe.command_responses.len() as u32,
-            })
-            .unwrap_or(0);
+        let Some(ack) = frame_ack(frame) else {
+            return Ok(());
+        };
         self.inner.write_all(&ack.to_be_bytes()).await?;
         self.inner.flush().await
     }
 
+    /// Writes the ACKs of a batch of frames in order, flushing once at the end.
+    ///
+    /// The bytes are the same as calling [`TeltonikaStream::write_frame_ack_async`] for each frame.
+    pub async fn write_frame_acks_async(&mut self, frames: &[&TeltonikaFrame]) -> io::Result<()> {
+        self.inner.write_all(&frame_acks(frames)).await?;
+        self.inner.flush().await
+    }
+
     pub async fn write_datagram_ack_async(
         &mut self,
         datagram: Option<&AVLDatagram>,
     ) -> io::Result<()> {
-        let (length, packet_id, avl_packet_id, ack) = match datagram {
-            Some(datagram) => (
-                datagram.records.len() as u16,
-                datagram.packet_id,
-                datagram.avl_packet_id,
-                datagram.records.len() as u32,
-            ),
-            None => (0, 0, 0, 0),
-        };
-        self.inner.write_all(&length.to_be_bytes()).await?;
-        self.inner.write_all(&packet_id.to_be_bytes()).await?;
-        self.inner.write_all(b"\x01").await?; // Non usable-byte
-        self.inner.write_all(&avl_packet_id.to_be_bytes()).await?;
-        self.inner.write_all(&ack.to_be_bytes()).await?;
+        self.inner.write_all(&datagram_ack(datagram)).await?;
         self.inner.flush().await
     }
 
-    /// Writes a series of commands to the stream.
+    /// Writes a series of commands to the stream using Codec 12.
     pub async fn write_commands_async(&mut self, commands: &[&str]) -> io::Result<()> {
-        let header_size = std::mem::size_of::<u32>() + // preamble
-        							std::mem::size_of::<u32>(); // data size
-
-        let data_size: usize = std::mem::size_of::<Codec>() + // codec
-        						std::mem::size_of::<u8>() + // command qty1
-              					std::mem::size_of::<u8>() + // command type
-                   				commands
-                       				.iter()
-                           			.fold(0, |acc, e| acc + (std::mem::size_of::<u32>() + e.bytes().len())) + // command size + command string
-                       			std::mem::size_of::<u8>(); // command qty2
-
-        let buffer_size = header_size + data_size + std::mem::size_of::<u32>(); // CRC 16
-
-        let mut commands_buffer = Vec::with_capacity(buffer_size);
-        commands_buffer.extend([0x00, 0x00, 0x00, 0x00].iter()); // preamble
-        commands_buffer.extend((data_size as u32).to_be_bytes().iter()); // data size
-        commands_buffer.push(Codec::C12.into()); // codec
-        commands_buffer.push(commands.len() as u8); // Qty1
-        commands_buffer.push(0x05u8); // Command type
-        commands_buffer.extend(commands.iter().flat_map(|command| {
-            let mut command_buffer =
-                Vec::with_capacity(std::mem::size_of::<u32>() + command.bytes().len());
-
-            command_buffer.extend((command.bytes().len() as u32).to_be_bytes());
-            command_buffer.extend(command.bytes()); // no call to to_be_bytes needed because it writes single bytes
-
-            command_buffer
-        }));
-        commands_buffer.push(commands.len() as u8); // Qty2
-        commands_buffer.extend(
-            (crate::crc16(&commands_buffer[header_size..]) as u32)
-                .to_be_bytes()
-                .iter(),
-        ); // crc 16
-
-        self.inner.write_all(&commands_buffer).await?;
+        self.write_commands_with_codec_async(CommandCodec::C12, commands)
+            .await
+    }
+
+    /// Writes a series of commands to the stream using the given GPRS codec.
+    ///
+    /// Codec 13 and 14 prefix every command with the timestamp or the IMEI they carry.
+    ///
+    /// # Errors
+    ///
+    /// If there are more than 255 commands or one is too long,
+    /// an error kind of [`std::io::ErrorKind::InvalidInput`] is returned.
+    pub async fn write_commands_with_codec_async(
+        &mut self,
+        codec: CommandCodec,
+        commands: &[&str],
+    ) -> io::Result<()> {
+        let bytes = encode_commands_with_codec(&codec, commands)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.inner.write_all(&bytes).await?;
         self.inner.flush().await
     }
 
@@ -477,4 +989,22 @@ impl<S: AsyncReadExt + AsyncWriteExt + Unpin> TeltonikaStream<S> {
     pub async fn write_command_async(&mut self, command: &str) -> io::Result<()> {
         self.write_commands_async(&[command]).await
     }
+
+    /// Sends commands using Codec 12 and reads their responses, see [`TeltonikaStream::send_commands`].
+    pub async fn send_commands_async(&mut self, commands: &[&str]) -> io::Result<CommandExchange> {
+        self.write_commands_async(commands).await?;
+        let mut avl_frames = vec![];
+        loop {
+            match self.read_frame_async().await? {
+                TeltonikaFrame::KeepAlive => continue,
+                TeltonikaFrame::GPRS(frame) => {
+                    return Ok(CommandExchange {
+                        responses: pair_responses(commands, frame)?,
+                        avl_frames,
+                    })
+                }
+                frame => avl_frames.push(frame),
+            }
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use std::{collections::HashMap, fmt};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{AVLEventIOValue, AVLRecord};
+
+/// Structure decoded from a variable length IO value by a [`VariableParser`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ParsedVariable {
+    Unsigned(u64),
+    Signed(i64),
+    Text(String),
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
+    Bytes(Vec<u8>),
+    List(Vec<ParsedVariable>),
+    /// Named fields, in the order they were decoded
+    Fields(Vec<(String, ParsedVariable)>),
+}
+
+/// Error returned by a [`VariableParser`] on bytes it cannot decode
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VariableParseError(pub String);
+
+impl fmt::Display for VariableParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid variable IO value: {}", self.0)
+    }
+}
+
+impl std::error::Error for VariableParseError {}
+
+/// Decodes the bytes of a variable length IO value, e.g. a list of Bluetooth sensors
+pub type VariableParser = fn(&[u8]) -> Result<ParsedVariable, VariableParseError>;
+
+/// Registry of the [`VariableParser`] of each IO id, empty by default
+///
+/// Values of the ids without a parser are kept as raw bytes.
+///
+/// ```rust
+/// use nom_teltonika::{parser::ParseOptions, ParsedVariable, VariableParsers};
+///
+/// let mut options = ParseOptions::default();
+/// options
+///     .variable_parsers
+///     .register(10_000, |bytes| Ok(ParsedVariable::Unsigned(bytes.len() as u64)));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct VariableParsers {
+    parsers: HashMap<u16, VariableParser>,
+}
+
+impl VariableParsers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the parser of an IO id, returning the one it replaces
+    pub fn register(&mut self, id: u16, parser: VariableParser) -> Option<VariableParser> {
+        self.parsers.insert(id, parser)
+    }
+
+    pub fn get(&self, id: u16) -> Option<VariableParser> {
+        self.parsers.get(&id).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parsers.is_empty()
+    }
+}
+
+impl AVLRecord {
+    /// Decodes the variable length IO values having a parser in `parsers`
+    /// into [`AVLEventIOValue::Parsed`], keeping their raw bytes.
+    ///
+    /// Values the parser fails on become [`AVLEventIOValue::ParseFailed`], keeping their raw bytes next to the error.
+    ///
+    /// Frames parsed with [`ParseOptions::variable_parsers`](crate::parser::ParseOptions::variable_parsers) set
+    /// already went through it.
+    pub fn parse_variables(&mut self, parsers: &VariableParsers) {
+        if parsers.is_empty() {
+            return;
+        }
+        for io in &mut self.io_events {
+            let Some(parser) = parsers.get(io.id) else {
+                continue;
+            };
+            let AVLEventIOValue::Variable(raw) = &mut io.value else {
+                continue;
+            };
+            match parser(raw) {
+                Ok(value) => {
+                    let raw = std::mem::take(raw);
+                    io.value = AVLEventIOValue::Parsed { raw, value };
+                }
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(id = io.id, %error, "Failed to parse a variable IO value");
+                    let raw = std::mem::take(raw);
+                    io.value = AVLEventIOValue::ParseFailed { raw, error };
+                }
+            }
+        }
+    }
+}
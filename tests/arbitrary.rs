@@ -0,0 +1,48 @@
+#![cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+use nom_teltonika::*;
+
+/// Deterministic pseudo random bytes
+fn data(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn arbitrary_frames_round_trip() {
+    for seed in 0..32 {
+        let data = data(seed, 4096);
+        let mut u = Unstructured::new(&data);
+
+        let frame = AVLFrame::arbitrary(&mut u).unwrap();
+        let bytes = frame.to_bytes().unwrap();
+        assert_eq!(AVLFrame::parse(&bytes).unwrap(), frame);
+
+        let datagram = AVLDatagram::arbitrary(&mut u).unwrap();
+        let bytes = datagram.to_bytes().unwrap();
+        assert_eq!(AVLDatagram::parse(&bytes).unwrap(), datagram);
+        assert!(datagram.imei.is_luhn_valid());
+    }
+}
+
+#[test]
+fn derive_arbitrary_on_containing_struct() {
+    #[derive(Arbitrary, Debug)]
+    #[allow(dead_code)]
+    struct Message {
+        record: AVLRecord,
+        io: AVLEventIO,
+    }
+
+    let data = data(0, 1024);
+    let message = Message::arbitrary(&mut Unstructured::new(&data)).unwrap();
+    assert!(message.record.longitude.abs() <= 180.0);
+    assert!(message.record.latitude.abs() <= 90.0);
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "embedded-io")]
+use std::convert::Infallible;
+
+use nom_teltonika::*;
+
+const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+
+/// Socket only implementing the [`embedded_io`] traits, returning a few bytes per read
+struct MockSocket {
+    input: Vec<u8>,
+    position: usize,
+    output: Vec<u8>,
+}
+
+impl embedded_io::ErrorType for MockSocket {
+    type Error = Infallible;
+}
+
+impl embedded_io::Read for MockSocket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let end = (self.position + 8).min(self.input.len());
+        let len = (end - self.position).min(buf.len());
+        buf[..len].copy_from_slice(&self.input[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl embedded_io::Write for MockSocket {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn read_frame_over_embedded_io() {
+    let mut input = vec![0x00, 0x0F];
+    input.extend(b"352093086403655");
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(EmbeddedIo(MockSocket {
+        input,
+        position: 0,
+        output: vec![],
+    }));
+
+    assert_eq!(stream.read_imei().unwrap().as_str(), "352093086403655");
+    stream.write_imei_approval().unwrap();
+    let frame = stream.read_frame().unwrap();
+    stream.write_frame_ack(Some(&frame)).unwrap();
+
+    let socket = stream.into_inner().into_inner();
+    assert_eq!(socket.output, [0x01, 0x00, 0x00, 0x00, 0x01]);
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "serde")]
+use nom_teltonika::*;
+
+const AVL_IDS: &str = r#"[
+    {
+        "id": 239,
+        "name": "Ignition",
+        "bytes": 1,
+        "type": "Unsigned",
+        "multiplier": "-",
+        "units": "-",
+        "min": 0,
+        "max": 1
+    },
+    {
+        "Property ID in AVL packet": "66",
+        "Property Name": "External Voltage",
+        "Bytes": "2",
+        "Type": "Unsigned",
+        "Value range Min": "0",
+        "Value range Max": "65535",
+        "Multiplier": "0.001",
+        "Units": "V"
+    },
+    {
+        "id": 72,
+        "name": "Dallas Temperature 1",
+        "bytes": 4,
+        "type": "Signed",
+        "multiplier": 0.1,
+        "units": "C",
+        "min": -550,
+        "max": 1150
+    },
+    {
+        "id": 385,
+        "name": "Beacon",
+        "bytes": "Variable",
+        "type": "HEX"
+    }
+]"#;
+
+#[test]
+fn load_teltonika_json() {
+    let map = IoIdMap::from_teltonika_json(AVL_IDS.as_bytes()).unwrap();
+    assert_eq!(map.len(), 4);
+
+    let voltage = map.get(66).unwrap();
+    assert_eq!(voltage.name, "External Voltage");
+    assert_eq!(voltage.bytes, Some(2));
+    assert_eq!(voltage.value_type, IoValueType::Unsigned);
+    assert_eq!(voltage.multiplier, 0.001);
+    assert_eq!(voltage.units.as_deref(), Some("V"));
+    assert_eq!(voltage.min, Some(0.0));
+    assert_eq!(voltage.max, Some(65535.0));
+
+    let ignition = map.get(239).unwrap();
+    assert_eq!(ignition.multiplier, 1.0);
+    assert_eq!(ignition.units, None);
+
+    assert_eq!(map.get(385).unwrap().bytes, None);
+}
+
+#[test]
+fn load_teltonika_json_missing_id() {
+    let err = IoIdMap::from_teltonika_json(r#"[{ "name": "Ignition" }]"#.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("AVL ID"));
+}
+
+#[test]
+fn describe_with_teltonika_json() {
+    let map = IoIdMap::from_teltonika_json(AVL_IDS.as_bytes()).unwrap();
+
+    let voltage = map
+        .describe(&AVLEventIO {
+            id: 66,
+            value: AVLEventIOValue::U16(12896),
+        })
+        .unwrap();
+    assert_eq!(voltage.name(), "External Voltage");
+    assert_eq!(voltage.value, DescribedValue::Number(12.896));
+    assert_eq!(voltage.to_string(), "External Voltage: 12.896 V");
+
+    let temperature = map
+        .describe(&AVLEventIO {
+            id: 72,
+            value: AVLEventIOValue::U32(-25i32 as u32),
+        })
+        .unwrap();
+    assert_eq!(temperature.value, DescribedValue::Number(-2.5));
+
+    let beacon = map
+        .describe(&AVLEventIO {
+            id: 385,
+            value: AVLEventIOValue::Variable(vec![0x01, 0xAB]),
+        })
+        .unwrap();
+    assert_eq!(beacon.value, DescribedValue::Text(String::from("0x01AB")));
+
+    assert!(map
+        .describe(&AVLEventIO {
+            id: 1,
+            value: AVLEventIOValue::U8(1),
+        })
+        .is_none());
+}
+
+#[test]
+fn record_io_json() {
+    let mut file = std::fs::File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    std::io::Read::read_to_end(&mut file, &mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let record = &frame.unwrap_avl().records[0];
+
+    let map = IoIdMap::from_teltonika_json(AVL_IDS.as_bytes()).unwrap();
+    let json = record.io_json(&map);
+
+    assert_eq!(json.len(), record.io_events.len());
+    assert_eq!(json["Ignition"], serde_json::json!(1));
+    assert_eq!(json["External Voltage"], serde_json::json!(12.896));
+    assert_eq!(json["io_240"], serde_json::json!(1));
+    assert_eq!(json["io_16"], serde_json::json!(3661976));
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "nmea")]
+use std::{fs::File, io::Read};
+
+use nom_teltonika::*;
+
+fn checksum(sentence: &str) -> u8 {
+    let (fields, _) = sentence[1..].split_once('*').unwrap();
+    fields.bytes().fold(0, |checksum, byte| checksum ^ byte)
+}
+
+#[test]
+fn gprmc_from_file() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let record = &frame.unwrap_avl().records[0];
+
+    let sentence = record.to_gprmc();
+    assert_eq!(
+        sentence,
+        "$GPRMC,140801.00,A,4403.8451,N,01227.2042,E,0.0,214.0,100621,,,A*58"
+    );
+    assert_eq!(
+        format!("{:02X}", checksum(&sentence)),
+        sentence[sentence.len() - 2..]
+    );
+}
+
+#[test]
+fn gprmc_southern_western_hemisphere() {
+    let input = hex::decode("00000000000000460801000001776D58189001FA0A1F00F1194D80009C009D05000F9B0D06EF01F0001505C80045019B0105B5000BB6000A424257430F8044000002F1000060191000000BE1000100006E2B").unwrap();
+    let (_, frame) = parser::tcp_frame(&input).unwrap();
+    let record = &frame.unwrap_avl().records[0];
+
+    let sentence = record.to_gprmc();
+    let fields: Vec<&str> = sentence.split(',').collect();
+    assert_eq!(fields[2], "A");
+    assert_eq!(&fields[3..7], ["2500.0000", "S", "01000.0000", "W"]);
+    assert_eq!(
+        format!("{:02X}", checksum(&sentence)),
+        sentence[sentence.len() - 2..]
+    );
+}
+
+#[test]
+fn gprmc_without_fix() {
+    let input = hex::decode("000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+    let (_, frame) = parser::tcp_frame(&input).unwrap();
+    let record = &frame.unwrap_avl().records[0];
+
+    let sentence = record.to_gprmc();
+    assert_eq!(sentence, "$GPRMC,100536.00,V,,,,,,,100619,,,N*73");
+    assert_eq!(
+        format!("{:02X}", checksum(&sentence)),
+        sentence[sentence.len() - 2..]
+    );
+}
@@ -21,6 +21,8 @@ fn parse_file() {
                     priority: Priority::Low,
                     longitude: 12.4534033,
                     latitude: 44.0640849,
+                    longitude_raw: 124534033,
+                    latitude_raw: 440640849,
                     altitude: 35,
                     angle: 214,
                     satellites: 14,
@@ -79,6 +81,8 @@ fn parse_file() {
                     priority: Priority::Low,
                     longitude: 12.4534033,
                     latitude: 44.0640849,
+                    longitude_raw: 124534033,
+                    latitude_raw: 440640849,
                     altitude: 35,
                     angle: 214,
                     satellites: 14,
@@ -137,6 +141,8 @@ fn parse_file() {
                     priority: Priority::Low,
                     longitude: 12.4534033,
                     latitude: 44.0640849,
+                    longitude_raw: 124534033,
+                    latitude_raw: 440640849,
                     altitude: 35,
                     angle: 214,
                     satellites: 13,
@@ -195,6 +201,8 @@ fn parse_file() {
                     priority: Priority::Low,
                     longitude: 12.4534033,
                     latitude: 44.0640849,
+                    longitude_raw: 124534033,
+                    latitude_raw: 440640849,
                     altitude: 35,
                     angle: 214,
                     satellites: 13,
@@ -253,6 +261,8 @@ fn parse_file() {
                     priority: Priority::Low,
                     longitude: 12.4534033,
                     latitude: 44.0640849,
+                    longitude_raw: 124534033,
+                    latitude_raw: 440640849,
                     altitude: 35,
                     angle: 214,
                     satellites: 13,
@@ -311,6 +321,8 @@ fn parse_file() {
                     priority: Priority::Low,
                     longitude: 12.4534033,
                     latitude: 44.0640849,
+                    longitude_raw: 124534033,
+                    latitude_raw: 440640849,
                     altitude: 35,
                     angle: 214,
                     satellites: 13,
@@ -369,3 +381,443 @@ fn parse_file() {
         }
     );
 }
+
+#[test]
+fn parse_file_raw_coordinates() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    for record in frame.unwrap_avl().records {
+        assert_eq!(record.longitude_raw, 124534033);
+        assert_eq!(record.latitude_raw, 440640849);
+        assert_eq!(record.longitude, record.longitude_raw as f64 / 10000000.0);
+        assert_eq!(record.latitude, record.latitude_raw as f64 / 10000000.0);
+    }
+}
+
+#[test]
+fn default_io_event() {
+    fn default_of<T: Default>() -> T {
+        T::default()
+    }
+
+    let io: AVLEventIO = default_of();
+    assert_eq!(io, AVLEventIO::new(0, AVLEventIOValue::U8(0)));
+    assert_eq!(default_of::<AVLEventIOValue>(), AVLEventIOValue::U8(0));
+}
+
+#[test]
+fn parse_negative_altitude() {
+    // Altitude 0xFFE2, 30 meters below sea level
+    let input = hex::decode("000000000000002808010000016B40D9AD80010000000000000000FFE200000000000103021503010101425E10000001000066DE").unwrap();
+    let (_, frame) = parser::tcp_frame(&input).unwrap();
+    let record = &frame.unwrap_avl().records[0];
+
+    assert_eq!(record.altitude, 65506);
+    assert_eq!(record.altitude_signed(), -30);
+}
+
+#[test]
+fn records_of_frame_and_datagram() {
+    fn max_speed(container: &impl HasRecords) -> Option<u16> {
+        container.records().iter().map(|record| record.speed).max()
+    }
+
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let frame = frame.unwrap_avl();
+    assert_eq!(frame.codec(), Codec::C8Ext);
+    assert_eq!(
+        max_speed(&frame),
+        frame.records.iter().map(|record| record.speed).max()
+    );
+
+    let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+    let (_, datagram) = parser::udp_datagram(&input).unwrap();
+    assert_eq!(datagram.codec(), Codec::C8);
+    assert_eq!(datagram.records().len(), 1);
+    assert_eq!(max_speed(&datagram), Some(0));
+}
+
+#[test]
+fn total_records_and_io_events() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    assert_eq!(frame.record_count(), 6);
+
+    let frame = frame.unwrap_avl();
+    assert_eq!(frame.total_records(), 6);
+    assert_eq!(frame.total_io_events(), 66);
+
+    let (_, frame) = parser::tcp_frame(&hex::decode("00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3").unwrap()).unwrap();
+    assert_eq!(frame.record_count(), 0);
+}
+
+#[test]
+fn parse_frame_with_remainder() {
+    let first = hex::decode("000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+    let second = hex::decode("000000000000004308020000016B40D57B480100000000000000000000000000000001010101000000000000016B40D5C198010000000000000000000000000000000101010101000000020000252C").unwrap();
+    let input = [first.clone(), second.clone()].concat();
+
+    let (frame, rest) = AVLFrame::parse_with_remainder(&input).unwrap();
+    assert_eq!(frame, AVLFrame::try_from(first.as_slice()).unwrap());
+    assert_eq!(rest, second);
+    // try_from silently drops the second frame
+    assert_eq!(AVLFrame::try_from(input.as_slice()).unwrap(), frame);
+
+    let (frame, rest) = AVLFrame::parse_with_remainder(rest).unwrap();
+    assert_eq!(frame.records.len(), 2);
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn sort_records_by_time() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let mut records = frame.unwrap_avl().records;
+    assert!(records.len() > 2);
+
+    let mut sorted = records.clone();
+    records.reverse();
+    records.swap(0, 1);
+    sorted.sort_by(AVLRecord::cmp_by_time);
+    sort_records(&mut records);
+
+    assert_eq!(records, sorted);
+    assert!(records
+        .windows(2)
+        .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+}
+
+#[test]
+fn group_records_into_trips() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let record = frame.unwrap_avl().records.remove(0);
+
+    let start = record.timestamp;
+    let records: Vec<AVLRecord> = [0, 60, 120, 3600, 3660]
+        .into_iter()
+        .map(|seconds| AVLRecord {
+            timestamp: start + chrono::Duration::seconds(seconds),
+            ..record.clone()
+        })
+        .collect();
+
+    let split: Vec<Vec<AVLRecord>> = trips(records.clone(), chrono::Duration::minutes(5)).collect();
+    assert_eq!(split, vec![records[..3].to_vec(), records[3..].to_vec()]);
+
+    // A gap equal to the maximum does not split the trip
+    assert_eq!(
+        trips(records.clone(), chrono::Duration::hours(1)).count(),
+        1
+    );
+    assert_eq!(trips(vec![], chrono::Duration::hours(1)).count(), 0);
+}
+
+#[test]
+fn parse_frame_from_hex() {
+    const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+    let frame = AVLFrame::from_hex(AVL_FRAME).unwrap();
+    assert_eq!(
+        frame,
+        AVLFrame::try_from(hex::decode(AVL_FRAME).unwrap().as_slice()).unwrap()
+    );
+
+    // Whitespace and lowercase digits are accepted
+    let spaced = format!("{}\n  {}", &AVL_FRAME[..40], AVL_FRAME[40..].to_lowercase());
+    assert_eq!(AVLFrame::from_hex(&spaced).unwrap(), frame);
+
+    assert_eq!(
+        AVLFrame::from_hex("0000 000Z"),
+        Err(FromHexError::InvalidCharacter { c: 'Z', index: 8 })
+    );
+    assert_eq!(AVLFrame::from_hex("000"), Err(FromHexError::OddLength));
+    assert!(matches!(
+        AVLFrame::from_hex(&AVL_FRAME[..40]),
+        Err(FromHexError::Parse(TeltonikaParseError::Truncated { .. }))
+    ));
+    assert_eq!(
+        AVLFrame::from_hex("00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3"),
+        Err(FromHexError::Parse(TeltonikaParseError::NotAvl))
+    );
+}
+
+#[test]
+fn parse_avl_frame_rejects_other_frames() {
+    let gprs = hex::decode("00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3").unwrap();
+    assert_eq!(AVLFrame::parse(&gprs), Err(TeltonikaParseError::NotAvl));
+    assert_eq!(
+        AVLFrame::try_from(gprs.as_slice()),
+        Err(TeltonikaParseError::NotAvl)
+    );
+    assert_eq!(
+        AVLFrame::parse(&[0x00; 4]),
+        Err(TeltonikaParseError::NotAvl)
+    );
+}
+
+#[test]
+fn parse_frame_prefixes_are_incomplete() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+
+    let (rest, _) = parser::tcp_frame(&buffer).unwrap();
+    let frame_length = buffer.len() - rest.len();
+
+    // Streaming parsers must ask for more bytes wherever the frame is cut, never fail
+    for end in 0..frame_length {
+        assert!(
+            matches!(
+                parser::tcp_frame(&buffer[..end]),
+                Err(nom::Err::Incomplete(_))
+            ),
+            "split at {end}"
+        );
+    }
+}
+
+#[test]
+fn records_in_time_window() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let frame = frame.unwrap_avl();
+
+    // Records are 5 seconds apart, the start is included and the end excluded
+    let window: Vec<&AVLRecord> = frame
+        .records_between(
+            "2021-06-10T14:08:06Z".parse().unwrap(),
+            "2021-06-10T14:08:21Z".parse().unwrap(),
+        )
+        .collect();
+    assert_eq!(window, frame.records[1..4].iter().collect::<Vec<_>>());
+
+    let empty = "2021-06-10T14:08:06Z".parse().unwrap();
+    assert_eq!(frame.records_between(empty, empty).count(), 0);
+
+    let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+    let (_, datagram) = parser::udp_datagram(&input).unwrap();
+    let timestamp = datagram.records[0].timestamp;
+    assert_eq!(
+        datagram
+            .records_between(timestamp, timestamp + chrono::Duration::milliseconds(1))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn merge_duplicate_io_ids() {
+    // External voltage (66) sent both as 1 and 2 bytes, ignition (239) twice as 1 byte
+    let input = hex::decode("000000000000002A08010000016B40D9AD80010000000000000000000000000000000004034201EF00EF0101422F3A0000010000A032").unwrap();
+    let (_, frame) = parser::tcp_frame(&input).unwrap();
+    let record = &frame.unwrap_avl().records[0];
+    assert_eq!(record.io_events.len(), 4);
+
+    let merged = record.merge_io();
+    assert_eq!(
+        merged.into_iter().collect::<Vec<_>>(),
+        vec![
+            (66, AVLEventIOValue::U16(12090)),
+            (239, AVLEventIOValue::U8(1)),
+        ]
+    );
+}
+
+#[test]
+fn variable_as_typed_arrays() {
+    let value = AVLEventIOValue::Variable(vec![0x00, 0x01, 0xFF, 0xC4, 0x12, 0x34]);
+    assert_eq!(value.as_u16_array(), Some(vec![0x0001, 0xFFC4, 0x1234]));
+    assert_eq!(value.as_u32_array(), None);
+    assert_eq!(value.as_u64_array(), None);
+
+    let empty = AVLEventIOValue::Variable(vec![]);
+    assert_eq!(empty.as_u32_array(), Some(vec![]));
+    assert_eq!(AVLEventIOValue::U16(1).as_u16_array(), None);
+}
+
+#[test]
+fn variable_as_little_endian_arrays() {
+    // 1200 and 65535 as u16, then 1 as u32, all little-endian
+    let value = AVLEventIOValue::Variable(vec![0xB0, 0x04, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00]);
+    assert_eq!(value.as_u16_array_le(), Some(vec![1200, 65535, 1, 0]));
+    assert_eq!(value.as_u32_array_le(), Some(vec![0xFFFF04B0, 1]));
+    assert_eq!(value.as_u64_array_le(), Some(vec![0x0000_0001_FFFF_04B0]));
+    assert_eq!(value.as_u16_array(), Some(vec![0xB004, 0xFFFF, 0x0100, 0]));
+    assert_eq!(
+        AVLEventIOValue::Variable(vec![0x01]).as_u16_array_le(),
+        None
+    );
+}
+
+#[test]
+fn convert_streaming_parser_errors() {
+    let mut input = hex::decode("000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A").unwrap();
+    let error = TeltonikaParseError::from(parser::tcp_frame(&input[..20]).unwrap_err());
+    assert_eq!(
+        error,
+        // Rest of the data field, the CRC is not asked for yet
+        TeltonikaParseError::Incomplete(nom::Needed::new(28))
+    );
+
+    // Wrong CRC
+    let length = input.len();
+    input[length - 1] ^= 0xFF;
+    let error = TeltonikaParseError::from(parser::tcp_frame(&input).unwrap_err());
+    assert_eq!(
+        error,
+        TeltonikaParseError::Streaming {
+            kind: nom::error::ErrorKind::Verify,
+            remaining: 4
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Invalid input 4 bytes before its end: predicate verification"
+    );
+
+    // With its input the offset of the failure is known
+    let error = TeltonikaParseError::from_nom(&input, parser::tcp_frame(&input).unwrap_err());
+    assert_eq!(
+        error,
+        TeltonikaParseError::Invalid {
+            kind: nom::error::ErrorKind::Verify,
+            offset: length - 4
+        }
+    );
+}
+
+#[test]
+fn group_mixed_frames() {
+    let frames = [
+        "000000000000004308020000016B40D57B480100000000000000000000000000000001010101000000000000016B40D5C198010000000000000000000000000000000101010101000000020000252C",
+        "00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3",
+        "000000000000004A8E010000016B412CEE000100000000000000000000000000000000010005000100010100010011001D00010010015E2C880002000B000000003544C87A000E000000001DD7E06A00000100002994",
+        "0000000000000000",
+    ];
+
+    let mut batch = FrameBatch::new();
+    assert!(batch.is_empty());
+    batch.extend(
+        frames
+            .iter()
+            .map(|frame| TeltonikaFrame::parse(&hex::decode(frame).unwrap()).unwrap()),
+    );
+
+    assert_eq!(batch.avl_frames().len(), 2);
+    assert_eq!(batch.gprs_frames().len(), 1);
+    assert_eq!(batch.avl_records().count(), 3);
+    assert_eq!(batch.avl_records_with_codec(Codec::C8).count(), 2);
+    assert_eq!(batch.avl_records_with_codec(Codec::C8Ext).count(), 1);
+    assert_eq!(batch.avl_records_with_codec(Codec::C16).count(), 0);
+    assert_eq!(
+        batch.gprs_responses().collect::<Vec<_>>(),
+        ["DI1:1 DI2:0 DI3:0 AIN1:0 AIN2:16924 DO1:0 DO2:1"]
+    );
+}
+
+#[test]
+fn distance_between_records() {
+    let input = hex::decode("000000000000004308020000016B40D57B480100000000000000000000000000000001010101000000000000016B40D5C198010000000000000000000000000000000101010101000000020000252C").unwrap();
+    let (_, frame) = parser::tcp_frame(&input).unwrap();
+    let mut records = frame.unwrap_avl().records;
+    assert_eq!(records[0].distance_to(&records[1]), None);
+
+    // Milan to Rome
+    (records[0].latitude, records[0].longitude) = (45.4642, 9.19);
+    (records[1].latitude, records[1].longitude) = (41.9028, 12.4964);
+    records[0].satellites = 8;
+    assert_eq!(records[0].distance_to(&records[1]), None);
+
+    records[1].satellites = 8;
+    let distance = records[0].distance_to(&records[1]).unwrap();
+    assert!((distance - 476_885.0).abs() < 1.0, "{distance}");
+    assert_eq!(records[1].distance_to(&records[0]), Some(distance));
+    assert_eq!(records[0].distance_to(&records[0]), Some(0.0));
+}
+
+#[test]
+fn iterate_enum_variants() {
+    assert_eq!(Codec::all().len(), 6);
+    for &codec in Codec::all() {
+        assert_eq!(Codec::try_from(u8::from(codec)), Ok(codec));
+    }
+
+    for &kind in Codec12Type::all() {
+        assert_eq!(Codec12Type::try_from(u8::from(kind)), Ok(kind));
+    }
+    assert_eq!(Codec12Type::all().len(), 3);
+
+    for &priority in Priority::all() {
+        assert_eq!(Priority::try_from(u8::from(priority)), Ok(priority));
+    }
+    assert_eq!(Priority::all().len(), 3);
+
+    // None has no wire value
+    for &cause in &EventGenerationCause::all()[1..] {
+        assert_eq!(EventGenerationCause::try_from(u8::from(cause)), Ok(cause));
+    }
+    assert_eq!(EventGenerationCause::all().len(), 9);
+}
+
+#[test]
+fn resync_cursor_past_coincidental_preambles() {
+    const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+    const GPRS_FRAME: &str = "00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3";
+
+    let avl = hex::decode(AVL_FRAME).unwrap();
+    let mut corrupted = avl.clone();
+    let length = corrupted.len();
+    corrupted[length - 1] ^= 0xFF;
+    // The zero coordinates of the record hold preambles, one followed by 4 more zeros
+    assert!(corrupted[12..].windows(8).any(|window| window == [0x00; 8]));
+
+    let mut capture = avl.clone();
+    capture.extend(&corrupted);
+    capture.extend(hex::decode(GPRS_FRAME).unwrap());
+
+    let mut cursor = FrameCursor::new(&capture);
+    assert!(matches!(cursor.next(), Some(TeltonikaFrame::AVL(_))));
+    assert!(matches!(cursor.next(), Some(TeltonikaFrame::GPRS(_))));
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.skipped(), corrupted.len());
+    assert!(cursor.remaining().is_empty());
+
+    // Nothing valid follows a truncated frame, it is left to read
+    let mut cursor = FrameCursor::new(&avl[..20]);
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.remaining(), &avl[..20]);
+}
+
+#[test]
+fn resync_cursor_past_invalid_priority() {
+    const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+
+    let avl = hex::decode(AVL_FRAME).unwrap();
+    // Unknown priority and a CRC no longer matching
+    let mut invalid = avl.clone();
+    invalid[18] = 0x07;
+    assert!(parser::tcp_frame(&invalid).is_err());
+
+    let mut capture = vec![0xAB, 0xCD];
+    capture.extend(&invalid);
+    capture.extend(&avl);
+
+    let mut cursor = FrameCursor::new(&capture);
+    assert!(matches!(cursor.next(), Some(TeltonikaFrame::AVL(_))));
+    assert_eq!(cursor.next(), None);
+    assert_eq!(cursor.skipped(), 2 + invalid.len());
+}
@@ -0,0 +1,36 @@
+use std::{fs::File, io::Read};
+
+use nom_teltonika::*;
+
+#[test]
+fn pretty_string_from_file() {
+    let mut file = File::open("tests/test.bin").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+
+    let mut voltage = IoIdDefinition::new(66, "External Voltage");
+    voltage.multiplier = 0.001;
+    voltage.units = Some("V".to_owned());
+    let map: IoIdMap = [IoIdDefinition::new(239, "Ignition"), voltage]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        frame.to_pretty_string(Some(&map)),
+        include_str!("test.pretty.txt")
+    );
+}
+
+#[test]
+fn display_io_values() {
+    assert_eq!(AVLEventIOValue::U32(25000).to_string(), "25000");
+    assert_eq!(
+        AVLEventIOValue::U64(u64::MAX).to_string(),
+        "18446744073709551615"
+    );
+    assert_eq!(
+        AVLEventIOValue::Variable(vec![0x00, 0x1A, 0xFF]).to_string(),
+        "0x001AFF"
+    );
+    assert_eq!(AVLEventIOValue::Variable(vec![]).to_string(), "0x");
+}
@@ -17,3 +17,138 @@ fn parse_file() {
     let writer = BufWriter::new(File::create("tests/test.json").expect("Can't create json file"));
     serde_json::to_writer_pretty(writer, &frame).expect("Can't serialize frame to json");
 }
+
+#[cfg(feature = "serde-typed")]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Typed(#[serde(with = "nom_teltonika::serde_typed")] AVLEventIOValue);
+
+#[cfg(feature = "serde-typed")]
+#[test]
+fn typed_io_value_round_trip() {
+    let values = [
+        (AVLEventIOValue::U8(1), r#"{"type":"u8","value":1}"#),
+        (AVLEventIOValue::U16(123), r#"{"type":"u16","value":123}"#),
+        (
+            AVLEventIOValue::U32(70000),
+            r#"{"type":"u32","value":70000}"#,
+        ),
+        (
+            AVLEventIOValue::U64(u64::MAX),
+            r#"{"type":"u64","value":18446744073709551615}"#,
+        ),
+        (
+            AVLEventIOValue::Variable(vec![0x01, 0x02]),
+            r#"{"type":"variable","value":"AQI="}"#,
+        ),
+    ];
+
+    for (value, json) in values {
+        let typed = Typed(value);
+        assert_eq!(serde_json::to_string(&typed).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Typed>(json).unwrap(), typed);
+    }
+
+    assert!(serde_json::from_str::<Typed>(r#"{"type":"variable","value":"!"}"#).is_err());
+}
+
+#[cfg(feature = "serde-typed")]
+#[test]
+fn typed_io_value_parse_failed() {
+    let typed = Typed(AVLEventIOValue::ParseFailed {
+        raw: vec![0x01, 0x02],
+        error: VariableParseError("odd length".into()),
+    });
+    let json = serde_json::to_string(&typed).unwrap();
+
+    assert_eq!(
+        json,
+        r#"{"type":"parse_failed","value":{"raw":"AQI=","error":"odd length"}}"#
+    );
+    assert_eq!(serde_json::from_str::<Typed>(&json).unwrap(), typed);
+}
+
+// Enabling serde-typed leaves the representation of the IO events as is
+#[test]
+fn externally_tagged_io_event() {
+    let io = AVLEventIO::new(66, AVLEventIOValue::U16(12896));
+    let json = serde_json::to_string(&io).unwrap();
+
+    assert_eq!(json, r#"{"id":66,"value":{"U16":12896}}"#);
+    assert_eq!(serde_json::from_str::<AVLEventIO>(&json).unwrap(), io);
+}
+
+#[test]
+fn json_frame_round_trip() {
+    let buffer = std::fs::read("tests/test.bin").unwrap();
+    let (_, frame) = parser::tcp_frame(&buffer).unwrap();
+    let frame = frame.unwrap_avl();
+
+    let mut json = Vec::new();
+    serde_json::to_writer_pretty(&mut json, &frame).unwrap();
+    let deserialized: AVLFrame = serde_json::from_slice(&json).unwrap();
+    assert_eq!(deserialized, frame);
+
+    let bytes = deserialized.to_bytes().unwrap();
+    assert_eq!(bytes, buffer[..bytes.len()]);
+    assert_eq!(AVLFrame::try_from(bytes.as_slice()).unwrap(), frame);
+}
+
+#[test]
+fn json_record_without_raw_coordinates() {
+    let buffer = std::fs::read("tests/test.bin").unwrap();
+    let frame = AVLFrame::try_from(buffer.as_slice()).unwrap();
+
+    let mut json = serde_json::to_value(&frame).unwrap();
+    let record = json["records"][0].as_object_mut().unwrap();
+    record.remove("longitude_raw");
+    record.remove("latitude_raw");
+    record["longitude"] = 45.0.into();
+
+    let deserialized: AVLFrame = serde_json::from_value(json).unwrap();
+    assert_eq!(deserialized.records[0].longitude_raw, 450_000_000);
+    assert_eq!(
+        deserialized.records[0].latitude_raw,
+        frame.records[0].latitude_raw
+    );
+    assert_eq!(deserialized.records[1..], frame.records[1..]);
+    assert!(deserialized.validate().is_ok());
+}
+
+#[test]
+fn json_variable_values_round_trip() {
+    let input = hex::decode("000000000000004A8E010000016B412CEE000100000000000000000000000000000000010005000100010100010011001D00010010015E2C880002000B000000003544C87A000E000000001DD7E06A00000100002994").unwrap();
+    let mut frame = AVLFrame::try_from(input.as_slice()).unwrap();
+    let record = &mut frame.records[0];
+    record.io_events.push(AVLEventIO::new(
+        10_000,
+        AVLEventIOValue::Variable(vec![0x01, 0x02]),
+    ));
+    record.io_events.push(AVLEventIO::new(
+        10_001,
+        AVLEventIOValue::Parsed {
+            raw: vec![0x03],
+            value: ParsedVariable::Unsigned(3),
+        },
+    ));
+
+    let json = serde_json::to_string_pretty(&frame).unwrap();
+    let deserialized: AVLFrame = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, frame);
+    assert_eq!(deserialized.to_bytes().unwrap(), frame.to_bytes().unwrap());
+}
+
+#[test]
+fn command_response_text_or_bytes() {
+    let text = CommandResponse::from("getinfo");
+    let binary = CommandResponse::from(vec![0x41, 0xFF]);
+    assert_eq!(serde_json::to_string(&text).unwrap(), r#""getinfo""#);
+    assert_eq!(serde_json::to_string(&binary).unwrap(), "[65,255]");
+
+    for response in [text, binary] {
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CommandResponse>(&json).unwrap(),
+            response
+        );
+    }
+}
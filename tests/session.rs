@@ -0,0 +1,104 @@
+use std::io::{self, Cursor, Read, Write};
+
+use nom_teltonika::*;
+
+const IMEI: &str = "000F333536333037303432343431303133";
+const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+
+/// Connection reading from `input` and writing to `output`
+struct Connection {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn session_handshake_and_frames() {
+    let mut input = hex::decode(IMEI).unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    input.extend(hex::decode("00000000").unwrap());
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut session = TeltonikaSession::new(TeltonikaStream::new(Connection {
+        input: Cursor::new(input),
+        output: vec![],
+    }));
+    assert_eq!(session.imei(), None);
+
+    let imei = session.read_imei().unwrap();
+    assert_eq!(imei, Imei::parse("356307042441013").unwrap());
+    assert_eq!(session.imei(), Some(&imei));
+    session.write_imei_approval().unwrap();
+
+    for expected in [1, 1, 2] {
+        let SessionFrame { imei: tag, frame } = session.read_frame().unwrap();
+        assert_eq!(tag, imei);
+        session.write_frame_ack(Some(&frame)).unwrap();
+        assert_eq!(session.frames_received(), expected);
+        assert_eq!(session.frames_acked(), expected);
+    }
+    assert_eq!(session.last_ack(), Some(1));
+
+    // Asking for a resend is not an acknowledgment
+    session.write_frame_ack(None).unwrap();
+    assert_eq!(session.frames_acked(), 2);
+    assert_eq!(session.last_ack(), Some(0));
+
+    let output = session.into_inner().into_inner().output;
+    assert_eq!(output, [1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0]);
+}
+
+#[test]
+fn session_frame_before_imei() {
+    let mut session = TeltonikaSession::new(TeltonikaStream::new(Connection {
+        input: Cursor::new(hex::decode(AVL_FRAME).unwrap()),
+        output: vec![],
+    }));
+
+    let err = session.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    assert_eq!(session.frames_received(), 0);
+    // Nothing was read
+    assert!(session.stream().buffered().is_empty());
+    assert_eq!(session.into_inner().into_inner().input.position(), 0);
+}
+
+#[test]
+fn pipelined_handshake_and_first_frame() {
+    let mut input = hex::decode(IMEI).unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Connection {
+        input: Cursor::new(input),
+        output: vec![],
+    });
+
+    let (imei, frame) = stream.handshake_and_first_frame().unwrap();
+    assert_eq!(imei.as_str(), "356307042441013");
+    assert_eq!(frame.unwrap().record_count(), 1);
+    assert!(stream.buffered().is_empty());
+    assert_eq!(stream.inner().output, [0x01]);
+
+    // The device waits for the approval before sending its first frame
+    let mut stream = TeltonikaStream::new(Connection {
+        input: Cursor::new(hex::decode(IMEI).unwrap()),
+        output: vec![],
+    });
+    let (imei, frame) = stream.handshake_and_first_frame().unwrap();
+    assert_eq!(imei.as_str(), "356307042441013");
+    assert_eq!(frame, None);
+    assert_eq!(stream.inner().output, [0x01]);
+}
@@ -0,0 +1,646 @@
+use std::{
+    io::{self, Cursor, Read, Write},
+    thread,
+    time::Duration,
+};
+
+use nom_teltonika::*;
+
+const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+const GPRS_FRAME: &str = "00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3";
+
+#[test]
+fn read_avl_then_gprs_frame() {
+    let mut input = hex::decode(AVL_FRAME).unwrap();
+    input.extend(hex::decode(GPRS_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    let mut frames = vec![];
+    for _ in 0..2 {
+        let frame = stream.read_frame().unwrap();
+        match &frame {
+            TeltonikaFrame::AVL(frame) => assert_eq!(frame.records.len(), 1),
+            TeltonikaFrame::GPRS(frame) => assert_eq!(
+                frame.command_responses,
+                vec!["DI1:1 DI2:0 DI3:0 AIN1:0 AIN2:16924 DO1:0 DO2:1"]
+            ),
+            TeltonikaFrame::KeepAlive => unreachable!(),
+        }
+        frames.push(frame);
+    }
+
+    assert!(matches!(frames[0], TeltonikaFrame::AVL(_)));
+    assert!(matches!(frames[1], TeltonikaFrame::GPRS(_)));
+}
+
+#[test]
+fn read_gprs_then_avl_frame_from_one_segment() {
+    let avl = hex::decode(AVL_FRAME).unwrap();
+    let mut input = hex::decode(GPRS_FRAME).unwrap();
+    input.extend(&avl);
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::GPRS(_)
+    ));
+    // Both frames came with the first read, the AVL one is waiting in the buffer
+    assert_eq!(stream.buffered(), avl);
+    assert_eq!(
+        stream.inner().position(),
+        stream.inner().get_ref().len() as u64
+    );
+
+    match stream.read_frame().unwrap() {
+        TeltonikaFrame::AVL(frame) => assert_eq!(frame.records.len(), 1),
+        frame => panic!("expected an AVL frame, got {frame:?}"),
+    }
+    assert!(stream.buffered().is_empty());
+}
+
+#[test]
+fn read_frames_split_across_reads() {
+    let mut input = hex::decode(AVL_FRAME).unwrap();
+    input.extend(hex::decode(GPRS_FRAME).unwrap());
+    let mut stream = TeltonikaStream::with_capacity(Cursor::new(input), 128, 7);
+
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::GPRS(_)
+    ));
+}
+
+/// Reader returning one byte at a time after waiting `delay`,
+/// or [`io::ErrorKind::WouldBlock`] once out of data.
+struct SlowReader {
+    data: Cursor<Vec<u8>>,
+    delay: Duration,
+}
+
+impl Read for SlowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        thread::sleep(self.delay);
+        match self.data.read(&mut buf[..1])? {
+            0 => Err(io::ErrorKind::WouldBlock.into()),
+            bytes_read => Ok(bytes_read),
+        }
+    }
+}
+
+impl Write for SlowReader {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn read_timeout_elapses_without_progress() {
+    let input = hex::decode(AVL_FRAME).unwrap();
+    let mut stream = TeltonikaStream::new(SlowReader {
+        data: Cursor::new(input[..20].to_vec()),
+        delay: Duration::from_millis(1),
+    });
+    stream.set_read_timeout(Some(Duration::from_millis(50)));
+
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn read_timeout_is_reset_by_progress() {
+    let input = hex::decode(AVL_FRAME).unwrap();
+    let mut stream = TeltonikaStream::new(SlowReader {
+        data: Cursor::new(input),
+        delay: Duration::from_millis(2),
+    });
+    // Reading the whole frame takes longer than the timeout but every read makes progress
+    stream.set_read_timeout(Some(Duration::from_millis(20)));
+
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+}
+
+#[test]
+fn read_without_timeout_propagates_would_block() {
+    let mut stream = TeltonikaStream::new(SlowReader {
+        data: Cursor::new(vec![]),
+        delay: Duration::ZERO,
+    });
+
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn write_command_as_codec12_command() {
+    let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+    stream.write_command("getinfo").unwrap();
+
+    let output = stream.into_inner().into_inner();
+    assert_eq!(
+        output,
+        hex::decode("000000000000000F0C010500000007676574696E666F0100004312").unwrap()
+    );
+    let (_, frame) = parser::tcp_frame(&output).unwrap();
+    assert_eq!(frame.unwrap_gprs().command_type, Codec12Type::Command);
+}
+
+#[test]
+fn encode_commands_without_stream() {
+    let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+    stream.write_command("getinfo").unwrap();
+
+    let bytes = encode_commands(&["getinfo"]).unwrap();
+    assert_eq!(
+        bytes,
+        hex::decode("000000000000000F0C010500000007676574696E666F0100004312").unwrap()
+    );
+    assert_eq!(bytes, stream.into_inner().into_inner());
+}
+
+#[test]
+fn encode_too_many_commands() {
+    assert_eq!(
+        encode_commands(&["getinfo"; 256]),
+        Err(ValidationError::TooManyCommands(256))
+    );
+}
+
+#[test]
+fn device_writes_frame_and_reads_ack() {
+    let frame = parser::tcp_frame(&hex::decode(AVL_FRAME).unwrap())
+        .unwrap()
+        .1
+        .unwrap_avl();
+
+    // Server side: read the frame sent by the device and acknowledge it
+    let mut device = TeltonikaStream::new(Cursor::new(vec![]));
+    device.write_frame(&frame).unwrap();
+    let mut server = TeltonikaStream::new(Cursor::new(device.into_inner().into_inner()));
+    let received = server.read_frame().unwrap();
+    assert_eq!(received, TeltonikaFrame::AVL(frame.clone()));
+
+    let mut acks = Cursor::new(vec![]);
+    TeltonikaStream::new(&mut acks)
+        .write_frame_ack(Some(&received))
+        .unwrap();
+    TeltonikaStream::new(&mut acks)
+        .write_frame_ack(None)
+        .unwrap();
+
+    // Device side: the first ACK accepts the frame, the second one asks for a resend
+    acks.set_position(0);
+    let mut device = TeltonikaStream::new(acks);
+    let ack = device.read_frame_ack().unwrap();
+    assert_eq!(ack, 1);
+    assert!(frame.is_ack_accepted(ack));
+    let ack = device.read_frame_ack().unwrap();
+    assert_eq!(ack, 0);
+    assert!(!frame.is_ack_accepted(ack));
+
+    let err = device.read_frame_ack().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+}
+
+#[test]
+fn write_commands_with_codec() {
+    let write = |codec| {
+        let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+        stream
+            .write_commands_with_codec(codec, &["getinfo"])
+            .unwrap();
+        hex::encode_upper(stream.into_inner().into_inner())
+    };
+
+    assert_eq!(
+        write(CommandCodec::C12),
+        "000000000000000F0C010500000007676574696E666F0100004312"
+    );
+    assert_eq!(
+        write(CommandCodec::C13 {
+            timestamp: chrono::DateTime::from_timestamp(1623334081, 0).unwrap(),
+        }),
+        "00000000000000130D01050000000B60C21CC1676574696E666F0100003DA9"
+    );
+
+    let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+    stream
+        .write_commands_with_codec(
+            CommandCodec::C14 {
+                imei: Imei::parse("352093081452251").unwrap(),
+            },
+            &["getver"],
+        )
+        .unwrap();
+    assert_eq!(
+        hex::encode_upper(stream.into_inner().into_inner()),
+        "00000000000000160E01050000000E0352093081452251676574766572010000D2C1"
+    );
+}
+
+#[test]
+fn write_too_many_commands() {
+    let commands = vec!["getinfo"; 256];
+    let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+    let error = stream.write_commands(&commands).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(stream.into_inner().into_inner().is_empty());
+
+    let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+    stream.write_commands(&commands[..255]).unwrap();
+    let bytes = stream.into_inner().into_inner();
+    assert_eq!(bytes[9], 255);
+}
+
+#[test]
+fn read_frame_with_empty_data() {
+    // A zero data length and CRC are read as keep-alives, the next frame is not lost
+    let mut input = hex::decode("000000000000000000000000").unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    for _ in 0..3 {
+        assert_eq!(stream.read_frame().unwrap(), TeltonikaFrame::KeepAlive);
+    }
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+}
+
+#[test]
+fn read_bare_keep_alive() {
+    // The device goes idle right after the keep-alive
+    let mut stream = TeltonikaStream::new(SlowReader {
+        data: Cursor::new(vec![0x00; 4]),
+        delay: Duration::from_millis(1),
+    });
+    stream.set_read_timeout(Some(Duration::from_millis(20)));
+    assert_eq!(stream.read_frame().unwrap(), TeltonikaFrame::KeepAlive);
+    assert!(stream.buffered().is_empty());
+}
+
+#[test]
+fn read_every_message() {
+    let mut input = hex::decode(AVL_FRAME).unwrap();
+    input.extend([0x00; 4]);
+    input.extend(hex::decode(GPRS_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    assert!(matches!(
+        stream.read_message().unwrap(),
+        TeltonikaMessage::Avl(_)
+    ));
+    assert_eq!(stream.read_message().unwrap(), TeltonikaMessage::KeepAlive);
+    assert!(matches!(
+        stream.read_message().unwrap(),
+        TeltonikaMessage::Command(_)
+    ));
+}
+
+#[test]
+fn read_keep_alive_then_frame() {
+    let mut input = hex::decode("00000000").unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    let keep_alive = stream.read_frame().unwrap();
+    assert_eq!(keep_alive, TeltonikaFrame::KeepAlive);
+    let frame = stream.read_frame().unwrap();
+    assert!(matches!(frame, TeltonikaFrame::AVL(_)));
+
+    // Only the frame is acknowledged
+    let mut acks = TeltonikaStream::new(Cursor::new(vec![]));
+    acks.write_frame_ack(Some(&keep_alive)).unwrap();
+    acks.write_frame_ack(Some(&frame)).unwrap();
+    acks.write_frame_acks(&[&keep_alive, &frame]).unwrap();
+    assert_eq!(acks.into_inner().into_inner(), [0, 0, 0, 1, 0, 0, 0, 1]);
+}
+
+#[test]
+fn read_gprs_frame_when_not_accepted() {
+    let mut input = hex::decode(GPRS_FRAME).unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+
+    let mut stream = TeltonikaStream::new(Cursor::new(input.clone()));
+    assert!(stream.accept_gprs());
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::GPRS(_)
+    ));
+
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+    stream.set_accept_gprs(false);
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    // The GPRS frame was consumed, the next one is read as usual
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+}
+
+#[test]
+fn write_frame_acks_batch() {
+    let mut input = hex::decode(AVL_FRAME).unwrap();
+    input.extend(hex::decode(GPRS_FRAME).unwrap());
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+    let frames: Vec<TeltonikaFrame> = (0..3).map(|_| stream.read_frame().unwrap()).collect();
+
+    let mut single = TeltonikaStream::new(Cursor::new(vec![]));
+    for frame in &frames {
+        single.write_frame_ack(Some(frame)).unwrap();
+    }
+    let mut batch = TeltonikaStream::new(Cursor::new(vec![]));
+    batch
+        .write_frame_acks(&frames.iter().collect::<Vec<_>>())
+        .unwrap();
+
+    let batch = batch.into_inner().into_inner();
+    assert_eq!(batch, single.into_inner().into_inner());
+    assert_eq!(batch, [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1]);
+}
+
+#[test]
+fn read_frame_exceeding_limits() {
+    // Codec 8 frame holding 2 records, each with 1 IO event
+    const TWO_RECORDS_FRAME: &str = "000000000000004308020000016B40D57B480100000000000000000000000000000001010101000000000000016B40D5C198010000000000000000000000000000000101010101000000020000252C";
+    let input = hex::decode(TWO_RECORDS_FRAME).unwrap();
+
+    let read = |max_records, max_io_per_record| {
+        let mut stream = TeltonikaStream::new(Cursor::new(input.clone()));
+        stream.set_parse_options(parser::ParseOptions {
+            max_records,
+            max_io_per_record,
+            ..Default::default()
+        });
+        stream.read_frame()
+    };
+
+    assert!(read(Some(2), Some(1)).is_ok());
+    assert_eq!(
+        read(Some(1), None).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert_eq!(
+        read(None, Some(0)).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+}
+
+/// Writer counting the calls to [`Write::write`]
+#[derive(Default)]
+struct CountingWriter {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl Read for CountingWriter {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes += 1;
+        self.data.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_each_message_at_once() {
+    let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+    let (_, datagram) = parser::udp_datagram(&input).unwrap();
+    let frame = parser::tcp_frame(&hex::decode(AVL_FRAME).unwrap())
+        .unwrap()
+        .1;
+
+    let writes = |write: &dyn Fn(&mut TeltonikaStream<CountingWriter>) -> io::Result<()>| {
+        let mut stream = TeltonikaStream::new(CountingWriter::default());
+        write(&mut stream).unwrap();
+        stream.into_inner().writes
+    };
+
+    assert_eq!(writes(&|s| s.write_datagram_ack(Some(&datagram))), 1);
+    assert_eq!(writes(&|s| s.write_frame(&frame.clone().unwrap_avl())), 1);
+    assert_eq!(writes(&|s| s.write_frame_acks(&[&frame, &frame])), 1);
+    assert_eq!(writes(&|s| s.write_commands(&["getinfo", "getver"])), 1);
+}
+
+#[test]
+fn write_datagram_ack_with_boundary_ids() {
+    let input = hex::decode("003DFFFF01FF000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+    let (_, datagram) = parser::udp_datagram(&input).unwrap();
+    assert_eq!(datagram.packet_id, 0xFFFF);
+    assert_eq!(datagram.avl_packet_id, 0xFF);
+
+    let mut stream = TeltonikaStream::new(Cursor::new(vec![]));
+    stream.write_datagram_ack(Some(&datagram)).unwrap();
+    assert_eq!(
+        hex::encode_upper(stream.into_inner().into_inner()),
+        "0001FFFF01FF00000001"
+    );
+}
+
+#[test]
+fn read_frame_sent_along_with_imei() {
+    let mut input = hex::decode("000F333536333037303432343431303133").unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    // A single read returns both the IMEI and the frame
+    assert_eq!(stream.read_imei().unwrap().as_str(), "356307042441013");
+    assert_eq!(stream.buffered(), hex::decode(AVL_FRAME).unwrap());
+    assert_eq!(
+        stream.inner().position(),
+        stream.inner().get_ref().len() as u64
+    );
+
+    let frame = stream.read_frame().unwrap();
+    assert_eq!(frame.record_count(), 1);
+    assert!(stream.buffered().is_empty());
+}
+
+#[test]
+fn read_bcd_imei() {
+    let mut input = hex::decode("0356307042441013").unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+    stream.set_imei_format(ImeiFormat::Bcd);
+    assert_eq!(stream.read_imei().unwrap().as_str(), "356307042441013");
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+
+    // Read as a length-prefixed IMEI, the packed digits declare 854 bytes
+    let mut stream = TeltonikaStream::new(Cursor::new(hex::decode("0356307042441013").unwrap()));
+    assert!(stream.read_imei().is_err());
+}
+
+/// Reader returning at most 16 bytes per read
+struct ChunkReader(Cursor<Vec<u8>>);
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(16);
+        self.0.read(&mut buf[..len])
+    }
+}
+
+impl Write for ChunkReader {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn read_large_frame_in_small_chunks() {
+    let mut input = std::fs::read("tests/test.bin").unwrap();
+    let (rest, expected) = parser::tcp_frame(&input).unwrap();
+    // Drop the padding following the frame
+    input.truncate(input.len() - rest.len());
+    assert!(input.len() > 16 * 10);
+
+    let mut stream = TeltonikaStream::new(ChunkReader(Cursor::new(input)));
+    assert_eq!(stream.read_frame().unwrap(), expected);
+    assert!(stream.buffered().is_empty());
+}
+
+#[test]
+fn parse_buffered_bytes_after_close() {
+    let input = hex::decode(AVL_FRAME).unwrap();
+    let mut stream = TeltonikaStream::new(SlowReader {
+        data: Cursor::new(input[..30].to_vec()),
+        delay: Duration::ZERO,
+    });
+    assert_eq!(stream.try_parse_buffered(), Ok(None));
+
+    // No more bytes arrive mid-frame
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+    assert_eq!(
+        stream.try_parse_buffered(),
+        Err(TeltonikaParseError::Truncated {
+            declared: input.len(),
+            available: 30
+        })
+    );
+    // The bytes are kept
+    assert_eq!(stream.buffered(), &input[..30]);
+}
+
+#[test]
+fn read_frame_after_bad_frame() {
+    let mut input = hex::decode(AVL_FRAME).unwrap();
+    // Corrupt the CRC
+    *input.last_mut().unwrap() ^= 0xFF;
+    input.extend(hex::decode(GPRS_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    // Only the bad frame was dropped
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::GPRS(_)
+    ));
+}
+
+#[test]
+fn read_frame_after_keep_alives() {
+    let mut input = vec![0x00; 8];
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    assert_eq!(stream.read_frame().unwrap(), TeltonikaFrame::KeepAlive);
+    assert_eq!(stream.read_frame().unwrap(), TeltonikaFrame::KeepAlive);
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+}
+
+#[test]
+fn read_frame_resyncs_after_bad_preamble() {
+    let mut input = hex::decode("DEADBEEF").unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+    let mut stream = TeltonikaStream::new(Cursor::new(input));
+
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(matches!(
+        stream.read_frame().unwrap(),
+        TeltonikaFrame::AVL(_)
+    ));
+}
+
+#[test]
+fn read_frame_closed_mid_frame() {
+    let input = hex::decode(AVL_FRAME).unwrap();
+    let mut stream = TeltonikaStream::new(Cursor::new(input[..30].to_vec()));
+
+    let err = stream.read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    // The bytes are kept
+    assert_eq!(stream.buffered(), &input[..30]);
+}
+
+#[test]
+fn send_commands_pairs_responses() {
+    const RESPONSES_FRAME: &str =
+        "000000000000001A0C0206000000095665723A30332E3138000000054750533A31020000C3EF";
+    let send = |input: &str, commands: &[&str]| {
+        let mut input = hex::decode(input).unwrap();
+        input.extend(hex::decode(RESPONSES_FRAME).unwrap());
+        let mut stream = TeltonikaStream::new(SlowReader {
+            data: Cursor::new(input),
+            delay: Duration::ZERO,
+        });
+        stream.send_commands(commands)
+    };
+
+    let responses = vec![
+        ("getver".to_owned(), "Ver:03.18".into()),
+        ("getgps".to_owned(), "GPS:1".into()),
+    ];
+    // Keep-alives received before the responses are skipped
+    assert_eq!(
+        send("00000000", &["getver", "getgps"]).unwrap(),
+        CommandExchange {
+            responses: responses.clone(),
+            avl_frames: vec![],
+        }
+    );
+    assert_eq!(
+        send("", &["getver"]).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    // AVL frames received before the responses are handed back to ACK
+    let exchange = send(&AVL_FRAME.repeat(2), &["getver", "getgps"]).unwrap();
+    assert_eq!(exchange.responses, responses);
+    assert_eq!(exchange.avl_frames.len(), 2);
+    assert!(matches!(exchange.avl_frames[0], TeltonikaFrame::AVL(_)));
+}
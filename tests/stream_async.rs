@@ -0,0 +1,217 @@
+#![cfg(feature = "tokio")]
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use nom_teltonika::*;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+
+#[tokio::test]
+async fn read_every_message_async() {
+    const GPRS_FRAME: &str = "00000000000000370C01060000002F4449313A31204449323A30204449333A302041494E313A302041494E323A313639323420444F313A3020444F323A3101000066E3";
+    let (mut device, server) = tokio::io::duplex(1024);
+    let mut stream = TeltonikaStream::new(server);
+
+    device
+        .write_all(&hex::decode(AVL_FRAME).unwrap())
+        .await
+        .unwrap();
+    device.write_all(&[0x00; 4]).await.unwrap();
+    device
+        .write_all(&hex::decode(GPRS_FRAME).unwrap())
+        .await
+        .unwrap();
+
+    let mut received = vec![];
+    for _ in 0..3 {
+        // The match is exhaustive: AVL data, command responses and keep-alives
+        let kind = match stream.read_message_async().await.unwrap() {
+            TeltonikaMessage::Avl(frame) => {
+                assert_eq!(frame.records.len(), 1);
+                "avl"
+            }
+            TeltonikaMessage::Command(frame) => {
+                assert_eq!(frame.command_responses.len(), 1);
+                "gprs"
+            }
+            TeltonikaMessage::KeepAlive => "keep-alive",
+        };
+        received.push(kind);
+    }
+    assert_eq!(received, ["avl", "keep-alive", "gprs"]);
+}
+
+#[tokio::test]
+async fn read_frame_async_after_cancellation() {
+    let input = hex::decode(AVL_FRAME).unwrap();
+    let (mut device, server) = tokio::io::duplex(1024);
+    let mut stream = TeltonikaStream::new(server);
+
+    // Only half of the frame arrives before the read is cancelled
+    device.write_all(&input[..20]).await.unwrap();
+    let cancelled =
+        tokio::time::timeout(Duration::from_millis(20), stream.read_frame_async()).await;
+    assert!(cancelled.is_err());
+
+    device.write_all(&input[20..]).await.unwrap();
+    let frame = stream.read_frame_async().await.unwrap().unwrap_avl();
+    assert_eq!(frame.records.len(), 1);
+}
+
+#[tokio::test]
+async fn send_datagram_ack_to_sender() {
+    let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+    let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let device = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    device
+        .send_to(&input, server.local_addr().unwrap())
+        .await
+        .unwrap();
+
+    let mut buffer = [0u8; 1024];
+    let (length, origin) = server.recv_from(&mut buffer).await.unwrap();
+    assert_eq!(origin, device.local_addr().unwrap());
+    let (_, datagram) = parser::udp_datagram(&buffer[..length]).unwrap();
+    send_datagram_ack(&server, origin, Some(&datagram))
+        .await
+        .unwrap();
+
+    let (length, origin) = device.recv_from(&mut buffer).await.unwrap();
+    assert_eq!(origin, server.local_addr().unwrap());
+    assert_eq!(hex::encode_upper(&buffer[..length]), "0001CAFE010500000001");
+}
+
+/// Writer counting the calls to [`AsyncWrite::poll_write`]
+#[derive(Default)]
+struct CountingWriter {
+    writes: usize,
+}
+
+impl AsyncRead for CountingWriter {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for CountingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writes += 1;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn write_datagram_ack_async_at_once() {
+    let input = hex::decode("003DCAFE0105000F33353230393330383634303336353508010000016B4F815B30010000000000000000000000000000000103021503010101425DBC000001").unwrap();
+    let (_, datagram) = parser::udp_datagram(&input).unwrap();
+
+    let mut stream = TeltonikaStream::new(CountingWriter::default());
+    stream
+        .write_datagram_ack_async(Some(&datagram))
+        .await
+        .unwrap();
+    stream
+        .write_commands_async(&["getinfo", "getver"])
+        .await
+        .unwrap();
+    assert_eq!(stream.into_inner().writes, 2);
+}
+
+/// Reader over fixed bytes counting the calls to [`AsyncRead::poll_read`]
+struct CountingReader {
+    data: Vec<u8>,
+    position: usize,
+    reads: usize,
+}
+
+impl CountingReader {
+    fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            position: 0,
+            reads: 0,
+        }
+    }
+}
+
+impl AsyncRead for CountingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.reads += 1;
+        let end = self.data.len().min(self.position + buf.remaining());
+        buf.put_slice(&self.data[self.position..end]);
+        self.position = end;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for CountingReader {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn buffered_stream_reads_less() {
+    // IMEI followed by frames sent without waiting, as some devices do after reconnecting
+    let mut input = hex::decode("000F333536333037303432343431303133").unwrap();
+    let frame = std::fs::read("tests/test.bin").unwrap();
+    let (_, frame) = parser::tcp_frame(&frame).unwrap();
+    let frame = frame.unwrap_avl();
+    for _ in 0..10 {
+        input.extend(frame.to_bytes().unwrap());
+    }
+
+    let mut plain = TeltonikaStream::new(CountingReader::new(input.clone()));
+    let mut buffered = TeltonikaStream::new_buffered(CountingReader::new(input));
+    plain.read_imei_async().await.unwrap();
+    buffered.read_imei_async().await.unwrap();
+    for _ in 0..10 {
+        assert_eq!(plain.read_frame_async().await.unwrap().unwrap_avl(), frame);
+        assert_eq!(
+            buffered.read_frame_async().await.unwrap().unwrap_avl(),
+            frame
+        );
+    }
+
+    let plain_reads = plain.into_inner().reads;
+    let buffered_reads = buffered.into_inner().into_inner().reads;
+    assert_eq!(buffered_reads, 1);
+    assert!(plain_reads > buffered_reads, "{plain_reads} reads");
+}
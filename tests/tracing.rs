@@ -0,0 +1,90 @@
+#![cfg(feature = "tracing")]
+use std::{
+    io::Cursor,
+    sync::{Arc, Mutex},
+};
+
+use nom_teltonika::*;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+const AVL_FRAME: &str = "000000000000002808010000016B40D9AD80010000000000000000000000000000000103021503010101425E100000010000F22A";
+
+/// Collects every field recorded on spans and events as `name=value`
+#[derive(Clone, Default)]
+struct Fields(Arc<Mutex<Vec<String>>>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Fields {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: Context<'_, S>) {
+        attrs.record(&mut self.clone());
+    }
+
+    fn on_record(&self, _span: &span::Id, values: &span::Record<'_>, _ctx: Context<'_, S>) {
+        values.record(&mut self.clone());
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        event.record(&mut self.clone());
+    }
+}
+
+fn capture(f: impl FnOnce()) -> Vec<String> {
+    let fields = Fields::default();
+    let subscriber = tracing_subscriber::registry().with(fields.clone());
+    tracing::subscriber::with_default(subscriber, f);
+    let fields = fields.0.lock().unwrap().clone();
+    fields
+}
+
+#[test]
+fn trace_read_frame() {
+    let fields = capture(|| {
+        let mut stream = TeltonikaStream::new(Cursor::new(hex::decode(AVL_FRAME).unwrap()));
+        stream.read_frame().unwrap();
+    });
+
+    assert!(fields.contains(&"codec=C8".to_owned()));
+    assert!(fields.contains(&"records=1".to_owned()));
+}
+
+#[test]
+fn trace_imei_on_read_frame() {
+    let mut input = hex::decode("000F333536333037303432343431303133").unwrap();
+    input.extend(hex::decode(AVL_FRAME).unwrap());
+
+    let fields = capture(|| {
+        let mut stream = TeltonikaStream::new(Cursor::new(input));
+        stream.read_imei().unwrap();
+        stream.read_frame().unwrap();
+    });
+
+    assert!(fields.contains(&"imei=\"356307042441013\"".to_owned()));
+}
+
+#[test]
+fn trace_crc_mismatch() {
+    let mut input = hex::decode(AVL_FRAME).unwrap();
+    let last = input.len() - 1;
+    input[last] = 0x2B;
+
+    let fields = capture(|| {
+        let mut stream = TeltonikaStream::new(Cursor::new(input));
+        stream.read_frame().unwrap_err();
+    });
+
+    assert!(fields.contains(&"expected=61994".to_owned()));
+    assert!(fields.contains(&"received=61995".to_owned()));
+    assert!(fields.contains(&"kind=Verify".to_owned()));
+}